@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use pinnacle_api::output;
+use pinnacle_api::util::Point;
+use pinnacle_api::util::Size;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::output_profiles::OutputInfo;
+use crate::state;
+
+const STATE_FILE: &str = "arrangements.toml";
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// the last-used arrangement for every set of outputs we've seen, as stored on disk.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SavedArrangements {
+    /// keyed by [`set_key`] of the connected outputs, then by output identity.
+    #[serde(default)]
+    pub arrangements: BTreeMap<String, BTreeMap<String, Position>>,
+}
+
+/// an output as seen by the arrangement logic.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArrangedOutput {
+    /// see [`OutputInfo::identity`].
+    pub identity: String,
+    /// the logical size, i.e. after scaling and transforms.
+    pub size: Size,
+    pub loc: Option<Point>,
+}
+
+/// a stable key for a set of outputs, independent of connection order.
+pub fn set_key<'a>(identities: impl IntoIterator<Item = &'a str>) -> String {
+    let mut identities = identities.into_iter().collect::<Vec<_>>();
+    identities.sort_unstable();
+    identities.join(" + ")
+}
+
+/// place outputs side by side, top-aligned, keeping the current left-to-right order. outputs that don't
+/// have a position yet go on the right.
+pub fn left_to_right(outputs: &[ArrangedOutput]) -> Vec<Point> {
+    let mut order = (0..outputs.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&idx| {
+        let output = &outputs[idx];
+        (
+            output.loc.is_none(),
+            output.loc.map(|loc| loc.x),
+            &output.identity,
+        )
+    });
+
+    let mut positions = vec![Point { x: 0, y: 0 }; outputs.len()];
+    let mut x = 0;
+    for idx in order {
+        positions[idx] = Point { x, y: 0 };
+        x += outputs[idx].size.w as i32;
+    }
+    positions
+}
+
+/// the saved arrangement for this set of outputs if there is one, left to right otherwise.
+pub fn arrange(saved: &SavedArrangements, outputs: &[ArrangedOutput]) -> Vec<Point> {
+    let key = set_key(outputs.iter().map(|output| output.identity.as_str()));
+    saved
+        .arrangements
+        .get(&key)
+        .and_then(|layout| {
+            outputs
+                .iter()
+                .map(|output| {
+                    let Position { x, y } = *layout.get(&output.identity)?;
+                    Some(Point { x, y })
+                })
+                .collect::<Option<Vec<_>>>()
+        })
+        .unwrap_or_else(|| left_to_right(outputs))
+}
+
+fn connected_outputs() -> (Vec<output::OutputHandle>, Vec<ArrangedOutput>) {
+    let handles = output::get_all().collect::<Vec<_>>();
+    let arranged = handles
+        .iter()
+        .map(|op| ArrangedOutput {
+            identity: OutputInfo::from_handle(op).identity(),
+            size: op.logical_size().unwrap_or(Size { w: 0, h: 0 }),
+            loc: op.loc(),
+        })
+        .collect();
+    (handles, arranged)
+}
+
+/// positions outputs when they're connected or disconnected, and remembers where they were put.
+#[derive(Debug, Default)]
+pub struct Arranger {
+    /// sets of outputs arranged during this session. moves are only remembered for these, so the
+    /// compositor's initial placement of a new output doesn't get saved as the user's choice.
+    arranged: Mutex<HashSet<String>>,
+    /// where the last arrangement put each output, by identity, until the outputs are all there. its moves
+    /// are only signalled once it's done, so they're told apart from the user's by where they lead.
+    arranging: Mutex<Option<HashMap<String, Point>>>,
+}
+
+impl Arranger {
+    /// arrange the connected outputs and save the result.
+    pub fn arrange_outputs(&self) {
+        let (handles, outputs) = connected_outputs();
+        if outputs.is_empty() {
+            return;
+        }
+        let saved = state::load::<SavedArrangements>(STATE_FILE);
+        let positions = arrange(&saved, &outputs);
+        *self.arranging.lock().unwrap() = Some(
+            outputs
+                .iter()
+                .map(|output| output.identity.clone())
+                .zip(positions.iter().copied())
+                .collect(),
+        );
+        for ((handle, output), Point { x, y }) in handles.iter().zip(&outputs).zip(&positions) {
+            tracing::info!(output = %output.identity, x, y, "positioning output");
+            handle.set_loc(*x, *y);
+        }
+
+        let key = set_key(outputs.iter().map(|output| output.identity.as_str()));
+        self.arranged.lock().unwrap().insert(key.clone());
+        Self::save(saved, key, &outputs, &positions);
+    }
+
+    /// remember the current positions, e.g. after an output was moved by hand. moves made by
+    /// [`Arranger::arrange_outputs`] are skipped, it saves its arrangement itself.
+    pub fn remember(&self) {
+        let (_, outputs) = connected_outputs();
+        let key = set_key(outputs.iter().map(|output| output.identity.as_str()));
+        if !self.arranged.lock().unwrap().contains(&key) {
+            return;
+        }
+        let Some(positions) = outputs
+            .iter()
+            .map(|output| output.loc)
+            .collect::<Option<Vec<_>>>()
+        else {
+            return;
+        };
+        {
+            let mut arranging = self.arranging.lock().unwrap();
+            if let Some(expected) = arranging.as_ref() {
+                let current = outputs
+                    .iter()
+                    .map(|output| output.identity.clone())
+                    .zip(positions.iter().copied())
+                    .collect::<HashMap<_, _>>();
+                let same_outputs = current.len() == expected.len()
+                    && current
+                        .keys()
+                        .all(|identity| expected.contains_key(identity));
+                if same_outputs {
+                    // still on the way, or just got there
+                    if current == *expected {
+                        *arranging = None;
+                    }
+                    return;
+                }
+                *arranging = None;
+            }
+        }
+        Self::save(state::load(STATE_FILE), key, &outputs, &positions);
+    }
+
+    fn save(
+        mut saved: SavedArrangements,
+        key: String,
+        outputs: &[ArrangedOutput],
+        positions: &[Point],
+    ) {
+        let layout = outputs
+            .iter()
+            .zip(positions)
+            .map(|(output, &Point { x, y })| (output.identity.clone(), Position { x, y }))
+            .collect();
+        if saved.arrangements.get(&key) != Some(&layout) {
+            saved.arrangements.insert(key, layout);
+            state::save(STATE_FILE, &saved);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(identity: &str, w: u32, loc: Option<(i32, i32)>) -> ArrangedOutput {
+        ArrangedOutput {
+            identity: identity.to_owned(),
+            size: Size { w, h: 1080 },
+            loc: loc.map(|(x, y)| Point { x, y }),
+        }
+    }
+
+    fn points(positions: &[(i32, i32)]) -> Vec<Point> {
+        positions.iter().map(|&(x, y)| Point { x, y }).collect()
+    }
+
+    #[test]
+    fn set_key_ignores_order() {
+        assert_eq!(set_key(["b", "a"]), set_key(["a", "b"]));
+        assert_eq!(set_key(["b", "a"]), "a + b");
+    }
+
+    #[test]
+    fn left_to_right_keeps_the_current_order() {
+        let outputs = [
+            output("a", 1920, Some((2560, 0))),
+            output("b", 2560, Some((0, 300))),
+            output("c", 1280, None),
+        ];
+        assert_eq!(
+            left_to_right(&outputs),
+            points(&[(2560, 0), (0, 0), (4480, 0)])
+        );
+    }
+
+    #[test]
+    fn left_to_right_places_new_outputs_by_identity() {
+        let outputs = [output("b", 1920, None), output("a", 2560, None)];
+        assert_eq!(left_to_right(&outputs), points(&[(2560, 0), (0, 0)]));
+        assert_eq!(left_to_right(&[]), []);
+    }
+
+    #[test]
+    fn arrange_uses_the_saved_arrangement() {
+        let outputs = [output("a", 1920, None), output("b", 1920, None)];
+        let mut saved = SavedArrangements::default();
+        saved.arrangements.insert(
+            set_key(["b", "a"]),
+            BTreeMap::from([
+                ("a".to_owned(), Position { x: 0, y: 1080 }),
+                ("b".to_owned(), Position { x: 0, y: 0 }),
+            ]),
+        );
+        assert_eq!(arrange(&saved, &outputs), points(&[(0, 1080), (0, 0)]));
+    }
+
+    #[test]
+    fn arrange_falls_back_to_left_to_right() {
+        let outputs = [output("a", 1920, None), output("b", 1920, None)];
+        let mut saved = SavedArrangements::default();
+        // saved for a different set of outputs
+        saved.arrangements.insert(
+            set_key(["a"]),
+            BTreeMap::from([("a".to_owned(), Position { x: 100, y: 0 })]),
+        );
+        assert_eq!(arrange(&saved, &outputs), points(&[(0, 0), (1920, 0)]));
+        // saved for this set, but missing an output
+        saved.arrangements.insert(
+            set_key(["a", "b"]),
+            BTreeMap::from([("a".to_owned(), Position { x: 100, y: 0 })]),
+        );
+        assert_eq!(arrange(&saved, &outputs), points(&[(0, 0), (1920, 0)]));
+    }
+}
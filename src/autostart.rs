@@ -0,0 +1,476 @@
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use futures::future::Shared;
+use futures::future::join_all;
+use pinnacle_api::output;
+use pinnacle_api::process::Command;
+use pinnacle_api::tag;
+use pinnacle_api::window::WindowHandle;
+use tokio::time::sleep;
+
+use crate::launch_context;
+use crate::supervisor;
+use crate::supervisor::{Readiness, Service, Spawn, Status, Supervisor};
+use crate::uwsm_command::UwsmCommand;
+
+/// how often an entry that something else is already starting is checked on.
+const STARTING_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// something to start with the session.
+#[derive(Debug, Clone)]
+pub struct AutostartEntry {
+    pub name: String,
+    pub command: Vec<String>,
+    /// spawn through `uwsm app` so the process gets its own systemd unit.
+    pub uwsm: bool,
+    /// only start it when the compositor starts, not on every config reload.
+    pub once: bool,
+    /// don't start it if it's already running.
+    pub unique: bool,
+    /// entries that have to be ready before this one is started. they always come earlier in the list.
+    pub after: Vec<String>,
+    pub ready: Readiness,
+    /// where to put the entry's first window. only used with [`Readiness::Window`], which says what the
+    /// window is.
+    pub output: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl AutostartEntry {
+    fn spawn(&self) -> Spawn {
+        let Some((program, args)) = self.command.split_first() else {
+            return Spawn::Failed;
+        };
+        let child = if self.uwsm {
+            let mut command = UwsmCommand::new(program).args(args);
+            if self.once {
+                command = command.once();
+            }
+            if self.unique {
+                command = command.unique();
+            }
+            command.spawn()
+        } else {
+            let mut command = Command::new(program);
+            command.args(args);
+            if self.once {
+                command.once();
+            }
+            if self.unique {
+                command.unique();
+            }
+            launch_context::spawn(&mut command)
+        };
+        Spawn::of(child, self.once || self.unique)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AutostartConfig {
+    pub entries: Vec<AutostartEntry>,
+    /// also start the XDG autostart entries in `~/.config/autostart`. with uwsm, systemd's
+    /// xdg-autostart-generator already does that.
+    pub xdg: bool,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Outcome {
+    Ready,
+    Failed,
+    /// not started because an entry it depends on failed.
+    Skipped,
+}
+
+/// what came of starting the autostart entries.
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub ready: Vec<String>,
+    pub failed: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ready", self.ready.len())?;
+        for (label, names) in [("failed", &self.failed), ("skipped", &self.skipped)] {
+            if !names.is_empty() {
+                write!(f, ", {} {label} ({})", names.len(), names.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// a window an entry was started for, waiting to be placed.
+#[derive(Debug)]
+struct Placement {
+    app_id: String,
+    output: Option<String>,
+    tag: Option<String>,
+}
+
+/// starts the autostart entries, each as soon as the entries it depends on are ready.
+#[derive(Debug)]
+pub struct Autostart {
+    entries: Vec<AutostartEntry>,
+    placements: Mutex<Vec<Placement>>,
+}
+
+impl Autostart {
+    pub fn new(config: AutostartConfig) -> Autostart {
+        let mut entries = config.entries;
+        if config.xdg {
+            entries.extend(xdg_entries());
+        }
+        Autostart {
+            entries,
+            placements: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// start all entries, concurrently where their dependencies allow, and report how it went once all
+    /// of them are ready or have failed.
+    pub async fn run(self: Arc<Self>, supervisor: Arc<Supervisor>) -> Summary {
+        let mut started: Vec<Shared<BoxFuture<'static, Outcome>>> = Vec::new();
+        for (idx, entry) in self.entries.iter().enumerate() {
+            // entries only depend on earlier ones, so their futures already exist
+            let dependencies = self.entries[..idx]
+                .iter()
+                .zip(&started)
+                .filter(|(dep, _)| entry.after.contains(&dep.name))
+                .map(|(_, outcome)| outcome.clone())
+                .collect::<Vec<_>>();
+            let start = {
+                let autostart = self.clone();
+                let supervisor = supervisor.clone();
+                async move {
+                    let outcomes = join_all(dependencies).await;
+                    if outcomes.iter().any(|outcome| *outcome != Outcome::Ready) {
+                        tracing::warn!(entry = %autostart.entries[idx].name, "not starting, a dependency failed");
+                        return Outcome::Skipped;
+                    }
+                    autostart.start(idx, &supervisor).await
+                }
+            };
+            started.push(start.boxed().shared());
+        }
+
+        let mut summary = Summary::default();
+        for (entry, outcome) in self.entries.iter().zip(join_all(started).await) {
+            let names = match outcome {
+                Outcome::Ready => &mut summary.ready,
+                Outcome::Failed => &mut summary.failed,
+                Outcome::Skipped => &mut summary.skipped,
+            };
+            names.push(entry.name.clone());
+        }
+        if summary.failed.is_empty() && summary.skipped.is_empty() {
+            tracing::info!("autostart: {summary}");
+            supervisor::notify("low", "autostart", &summary.to_string());
+        } else {
+            tracing::error!("autostart: {summary}");
+            supervisor::notify("critical", "autostart", &summary.to_string());
+        }
+        summary
+    }
+
+    async fn start(self: Arc<Self>, idx: usize, supervisor: &Supervisor) -> Outcome {
+        let entry = &self.entries[idx];
+        let name = entry.name.clone();
+        let service = Service::with_spawn(&name, entry.ready.clone(), {
+            let autostart = self.clone();
+            move || {
+                let entry = &autostart.entries[idx];
+                let spawn = entry.spawn();
+                if let Spawn::Child(_) = spawn {
+                    autostart.expect_window(entry);
+                }
+                spawn
+            }
+        });
+        let mut status = supervisor.run(service).await;
+        // `Starting` means something else is already starting it, so wait for that to finish
+        while let Status::Starting { .. } = status {
+            sleep(STARTING_POLL_INTERVAL).await;
+            status = supervisor.status(&name).unwrap_or(status);
+        }
+        match status {
+            Status::Ready => Outcome::Ready,
+            Status::Starting { .. } | Status::Failed { .. } => Outcome::Failed,
+        }
+    }
+
+    /// remember where the window of `entry` should go once it's mapped.
+    fn expect_window(&self, entry: &AutostartEntry) {
+        if let Readiness::Window { app_id, .. } = &entry.ready
+            && (entry.output.is_some() || entry.tag.is_some())
+        {
+            self.placements.lock().unwrap().push(Placement {
+                app_id: app_id.clone(),
+                output: entry.output.clone(),
+                tag: entry.tag.clone(),
+            });
+        }
+    }
+
+    /// put `window` on the output and tag of the entry that started it.
+    pub fn place(&self, window: &WindowHandle) {
+        let app_id = window.app_id();
+        let placement = {
+            let mut placements = self.placements.lock().unwrap();
+            let Some(idx) = placements.iter().position(|p| p.app_id == app_id) else {
+                return;
+            };
+            placements.remove(idx)
+        };
+        let output = match &placement.output {
+            Some(name) => output::get_by_name(name),
+            None => output::get_focused(),
+        };
+        let Some(output) = output else {
+            tracing::warn!(output = ?placement.output, %app_id, "autostart output isn't connected");
+            return;
+        };
+        match placement.tag {
+            Some(name) => match tag::get_on_output(&name, &output) {
+                Some(tag) => window.move_to_tag(&tag),
+                None => tracing::warn!(tag = %name, %app_id, "autostart tag doesn't exist"),
+            },
+            None => window.set_tags(output.active_tags()),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/autostart`, falling back to `~/.config/autostart`.
+fn xdg_autostart_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("autostart"))
+}
+
+/// the XDG autostart entries that apply to this session, in file name order.
+fn xdg_entries() -> Vec<AutostartEntry> {
+    let Some(dir) = xdg_autostart_dir() else {
+        return Vec::new();
+    };
+    let mut paths = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+            .collect::<Vec<_>>(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(err) => {
+            tracing::warn!(dir = %dir.display(), "failed to read the autostart directory: {err}");
+            return Vec::new();
+        }
+    };
+    paths.sort();
+
+    let desktops = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+    let desktops = desktops.split(':').collect::<Vec<_>>();
+    paths
+        .iter()
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            let contents = fs::read_to_string(path)
+                .map_err(|err| tracing::warn!(path = %path.display(), "failed to read: {err}"))
+                .ok()?;
+            parse_desktop_entry(&name, &contents, &desktops)
+        })
+        .collect()
+}
+
+/// an autostart entry from the contents of a `.desktop` file, unless it's hidden, disabled or meant for
+/// other desktops.
+fn parse_desktop_entry(name: &str, contents: &str, desktops: &[&str]) -> Option<AutostartEntry> {
+    let mut in_entry = false;
+    let mut exec = None;
+    let mut try_exec = None;
+    let mut only_show_in = None;
+    let mut not_show_in = None;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if !in_entry {
+            continue;
+        }
+        match (key.trim(), value.trim()) {
+            ("Type", value) if value != "Application" => return None,
+            ("Hidden", "true") | ("X-GNOME-Autostart-enabled", "false") => return None,
+            ("Exec", value) => exec = Some(value.to_owned()),
+            ("TryExec", value) => try_exec = Some(value.to_owned()),
+            ("OnlyShowIn", value) => only_show_in = Some(value.to_owned()),
+            ("NotShowIn", value) => not_show_in = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+
+    let listed = |list: &str| list.split(';').any(|desktop| desktops.contains(&desktop));
+    if only_show_in.is_some_and(|list| !listed(&list))
+        || not_show_in.is_some_and(|list| listed(&list))
+        || try_exec.is_some_and(|program| !is_executable(&program))
+    {
+        return None;
+    }
+    let command = split_exec(&exec?);
+    if command.is_empty() {
+        return None;
+    }
+    Some(AutostartEntry {
+        name: name.to_owned(),
+        command,
+        uwsm: true,
+        once: true,
+        unique: false,
+        after: Vec::new(),
+        ready: Readiness::Spawned,
+        output: None,
+        tag: None,
+    })
+}
+
+/// whether `program` is an absolute path or can be found on `$PATH`.
+fn is_executable(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).exists();
+    }
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(program).exists()))
+}
+
+/// split the `Exec` key of a desktop entry into arguments, undoing its quoting and dropping the field
+/// codes, which have nothing to expand to when autostarting.
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = None::<String>;
+    let mut quoted = false;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                arg.get_or_insert_default();
+            }
+            '\\' if quoted => {
+                if let Some(escaped) = chars.next() {
+                    arg.get_or_insert_default().push(escaped);
+                }
+            }
+            // `%%` is a literal `%`, anything else is a field code. a field code on its own doesn't leave
+            // an empty argument behind
+            '%' => {
+                if chars.next() == Some('%') {
+                    arg.get_or_insert_default().push('%');
+                }
+            }
+            c if c.is_whitespace() && !quoted => args.extend(arg.take()),
+            c => arg.get_or_insert_default().push(c),
+        }
+    }
+    args.extend(arg);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Option<Vec<String>> {
+        parse_desktop_entry("test", contents, &["pinnacle"]).map(|entry| entry.command)
+    }
+
+    #[test]
+    fn split_exec_drops_field_codes() {
+        assert_eq!(split_exec("foot %U"), ["foot"]);
+        assert_eq!(split_exec("app --file=%f %i %c"), ["app", "--file="]);
+        assert_eq!(split_exec("%k app"), ["app"]);
+    }
+
+    #[test]
+    fn split_exec_keeps_literal_percent_signs() {
+        assert_eq!(split_exec("printf 100%%"), ["printf", "100%"]);
+        assert_eq!(split_exec("printf \"%%d\""), ["printf", "%d"]);
+    }
+
+    #[test]
+    fn split_exec_undoes_quoting() {
+        assert_eq!(
+            split_exec(r#""/opt/my app/bin"  --name "say \"hi\"" "a\\b""#),
+            ["/opt/my app/bin", "--name", "say \"hi\"", "a\\b"]
+        );
+        assert_eq!(split_exec(r#"app "" x"#), ["app", "", "x"]);
+        assert_eq!(split_exec("  "), Vec::<String>::new());
+    }
+
+    #[test]
+    fn desktop_entry_is_read_from_its_group() {
+        let contents = "\
+[Desktop Entry]
+Type=Application
+Name=Syncthing
+Exec=syncthing serve --no-browser
+
+[Desktop Action Open]
+Exec=syncthing browser
+";
+        let entry = parse_desktop_entry("syncthing", contents, &["pinnacle"]).unwrap();
+        assert_eq!(entry.name, "syncthing");
+        assert_eq!(entry.command, ["syncthing", "serve", "--no-browser"]);
+        assert!(entry.uwsm && entry.once);
+    }
+
+    #[test]
+    fn hidden_and_disabled_entries_are_skipped() {
+        assert_eq!(parse("[Desktop Entry]\nExec=app\nHidden=true\n"), None);
+        assert_eq!(
+            parse("[Desktop Entry]\nExec=app\nX-GNOME-Autostart-enabled=false\n"),
+            None
+        );
+        assert_eq!(parse("[Desktop Entry]\nType=Link\nExec=app\n"), None);
+        assert_eq!(parse("[Desktop Entry]\nName=app\n"), None);
+        assert_eq!(parse("[Desktop Entry]\nExec=%U\n"), None);
+        assert_eq!(
+            parse("[Desktop Entry]\nExec=app\nTryExec=/nonexistent/app\n"),
+            None
+        );
+        assert_eq!(
+            parse("[Desktop Entry]\nExec=app\nHidden=false\n"),
+            Some(vec!["app".to_owned()])
+        );
+    }
+
+    #[test]
+    fn entries_for_other_desktops_are_skipped() {
+        assert_eq!(
+            parse("[Desktop Entry]\nExec=app\nOnlyShowIn=GNOME;KDE;\n"),
+            None
+        );
+        assert_eq!(
+            parse("[Desktop Entry]\nExec=app\nOnlyShowIn=GNOME;pinnacle;\n"),
+            Some(vec!["app".to_owned()])
+        );
+        assert_eq!(
+            parse("[Desktop Entry]\nExec=app\nNotShowIn=pinnacle;\n"),
+            None
+        );
+        assert_eq!(
+            parse("[Desktop Entry]\nExec=app\nNotShowIn=GNOME;\n"),
+            Some(vec!["app".to_owned()])
+        );
+    }
+}
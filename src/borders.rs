@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use pinnacle_api::experimental::snowcap_api::widget::Color;
+use pinnacle_api::output::OutputHandle;
+use pinnacle_api::snowcap::DecorationHandle;
+use pinnacle_api::snowcap::FocusBorder;
+use pinnacle_api::window;
+use pinnacle_api::window::LayoutMode;
+use pinnacle_api::window::WindowHandle;
+
+use crate::sticky::Sticky;
+use crate::theme::Rgba;
+use crate::theme::Theme;
+use crate::theme::WindowState;
+use crate::urgency::Urgency;
+
+fn color(rgba: Rgba) -> Color {
+    let [r, g, b, a] = rgba.fractions();
+    Color::rgba(r, g, b, a)
+}
+
+/// how a border is drawn: focused color, unfocused color and thickness. `None` means no border.
+type Look = Option<(Rgba, Rgba, u32)>;
+
+/// whether `window` is the only window on each of its tags.
+fn alone(window: &WindowHandle) -> bool {
+    window.tags().all(|tag| tag.windows().count() <= 1)
+}
+
+fn look(window: &WindowHandle, theme: &Theme, urgent: bool, sticky: bool) -> Look {
+    let layout_mode = window.layout_mode();
+    // fullscreen windows are meant to have the screen to themselves, and a lone window is obviously
+    // the focused one. urgent windows keep their border so they can still be spotted
+    if layout_mode == LayoutMode::Fullscreen || (!urgent && theme.smart_borders && alone(window)) {
+        return None;
+    }
+    let style = theme.border_for(&window.app_id());
+    let (focused, unfocused) = style.colors(WindowState {
+        urgent,
+        sticky,
+        floating: layout_mode == LayoutMode::Floating,
+        maximized: layout_mode == LayoutMode::Maximized,
+    });
+    Some((focused, unfocused, style.thickness))
+}
+
+fn make_fb(
+    win: &WindowHandle,
+    (focused, unfocused, thickness): (Rgba, Rgba, u32),
+) -> Option<DecorationHandle> {
+    FocusBorder {
+        unfocused_color: color(unfocused),
+        focused_color: color(focused),
+        thickness,
+        ..FocusBorder::new(win)
+    }
+    .decorate()
+    .inspect_err(|err| {
+        tracing::warn!(app_id = %win.app_id(), "failed to decorate window: {err}");
+    })
+    .ok()
+}
+
+struct Decoration {
+    handle: Option<DecorationHandle>,
+    look: Look,
+}
+
+/// the focus borders of all windows, so they can be redrawn when a window's state or the theme changes.
+pub struct Borders {
+    theme: Mutex<Theme>,
+    urgency: Arc<Urgency>,
+    sticky: Arc<Sticky>,
+    decorations: Mutex<HashMap<WindowHandle, Decoration>>,
+}
+
+impl Borders {
+    pub fn new(theme: Theme, urgency: Arc<Urgency>, sticky: Arc<Sticky>) -> Borders {
+        Borders {
+            theme: Mutex::new(theme),
+            urgency,
+            sticky,
+            decorations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// draw the border of `window` for its current state. the border is only redrawn when it would look
+    /// different.
+    pub fn decorate(&self, window: &WindowHandle) {
+        let look = look(
+            window,
+            &self.theme.lock().unwrap(),
+            self.urgency.is_urgent(window),
+            self.sticky.is_sticky(window),
+        );
+        let mut decorations = self.decorations.lock().unwrap();
+        if decorations
+            .get(window)
+            .is_some_and(|current| current.look == look)
+        {
+            return;
+        }
+        if let Some(old) = decorations.remove(window).and_then(|old| old.handle) {
+            old.close();
+        }
+        let handle = look.and_then(|look| make_fb(window, look));
+        decorations.insert(window.clone(), Decoration { handle, look });
+    }
+
+    pub fn forget(&self, window: &WindowHandle) {
+        self.decorations.lock().unwrap().remove(window);
+    }
+
+    /// redraw the borders of the windows on `output`, e.g. after windows came or went and smart borders
+    /// need to show or hide.
+    pub fn refresh(&self, output: &OutputHandle) {
+        for window in self.decorated() {
+            if window.output().is_some_and(|op| op == *output) {
+                self.decorate(&window);
+            }
+        }
+    }
+
+    /// switch to `theme` and redraw every border with it.
+    pub fn set_theme(&self, theme: Theme) {
+        {
+            let mut current = self.theme.lock().unwrap();
+            if *current == theme {
+                return;
+            }
+            *current = theme;
+        }
+        tracing::info!("theme changed, redrawing borders");
+        for window in self.decorated() {
+            self.decorate(&window);
+        }
+    }
+
+    /// the live windows with a border. dead windows are dropped.
+    fn decorated(&self) -> Vec<WindowHandle> {
+        let alive = window::get_all().collect::<Vec<_>>();
+        let mut decorations = self.decorations.lock().unwrap();
+        decorations.retain(|window, _| alive.contains(window));
+        decorations.keys().cloned().collect()
+    }
+}
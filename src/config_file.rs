@@ -0,0 +1,1476 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use pinnacle_api::input;
+use pinnacle_api::input::Bind;
+use pinnacle_api::input::Mod;
+use pinnacle_api::output;
+use pinnacle_api::process::Command;
+use pinnacle_api::util::Point;
+use pinnacle_api::util::Size;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::autostart::{AutostartConfig, AutostartEntry};
+use crate::launch_context;
+use crate::layouts::{GapSizes, LayoutKind};
+use crate::output_profiles::{
+    ModeSpec, OutputMatcher, OutputSettings, Profile, ProfileOutput, parse_transform,
+};
+use crate::scratchpads;
+use crate::scratchpads::Scratchpad;
+use crate::supervisor::Readiness;
+use crate::swallowing::SwallowConfig;
+use crate::theme::{BorderStyle, Rgba, StyleOverride, Theme};
+use crate::urgency::AttentionRule;
+use crate::uwsm_command::UwsmCommand;
+use crate::window_rules::{Action, Decoration, Geometry, Matcher, RuleSet, VrrPolicy, WindowRule};
+
+/// name of the config file, looked up next to `pinnacle.toml`.
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// the config file shipped with the config. sections missing from the user's file, or too broken to be
+/// read, are taken from here.
+const DEFAULT_CONFIG: &str = include_str!("../config.toml");
+
+/// chords `main.rs` binds itself, apart from the per-tag binds. a config bind or scratchpad on one of these
+/// would fire alongside the built-in one, so it is rejected instead. keep this in sync with `main.rs`.
+const BUILTIN_BINDS: &[&str] = &[
+    "mod+t",
+    "mod+q",
+    "mod+shift+q",
+    "mod+ctrl+shift+q",
+    "mod+Escape",
+    "mod+s",
+    "XF86AudioLowerVolume",
+    "XF86AudioRaiseVolume",
+    "mod+shift+c",
+    "mod+Delete",
+    "mod+ctrl+Delete",
+    "mod+shift+Delete",
+    "mod+ctrl+u",
+    "mod+ctrl+i",
+    "mod+j",
+    "mod+k",
+    "mod+Tab",
+    "mod+shift+Tab",
+    "mod+BackSpace",
+    "mod+u",
+    "mod+ctrl+j",
+    "mod+ctrl+k",
+    "mod+Left",
+    "mod+Right",
+    "mod+Up",
+    "mod+Down",
+    "mod+shift+Left",
+    "mod+shift+Right",
+    "mod+shift+Up",
+    "mod+shift+Down",
+    "mod+ctrl+Right",
+    "mod+ctrl+Left",
+    "mod+ctrl+space",
+    "mod+f",
+    "mod+m",
+    "mod+ctrl+s",
+    "mod+space",
+    "mod+shift+space",
+    "mod+shift+j",
+    "mod+shift+k",
+    "mod+h",
+    "mod+l",
+    "mod+comma",
+    "mod+period",
+    "mod+equal",
+    "mod+ctrl+minus",
+    "mod+ctrl+equal",
+];
+
+/// the runtime configuration loaded from [`CONFIG_FILE_NAME`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub terminal: String,
+    pub tags: Vec<String>,
+    pub binds: Vec<BindConfig>,
+    pub rules: RuleSet,
+    pub outputs: OutputsConfig,
+    pub layouts: LayoutsConfig,
+    pub scratchpads: Vec<Scratchpad>,
+    pub attention: Vec<AttentionRule>,
+    pub theme: Theme,
+    pub swallow: SwallowConfig,
+    pub autostart: AutostartConfig,
+}
+
+impl Default for Config {
+    /// what a setting falls back to when its value is invalid. whole sections fall back to
+    /// [`DEFAULT_CONFIG`] instead.
+    fn default() -> Self {
+        Config {
+            terminal: "wezterm".to_owned(),
+            tags: ["I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX", "X"]
+                .map(String::from)
+                .to_vec(),
+            binds: Vec::new(),
+            rules: RuleSet::default(),
+            outputs: OutputsConfig::default(),
+            layouts: LayoutsConfig::default(),
+            scratchpads: Vec::new(),
+            attention: Vec::new(),
+            theme: Theme::default(),
+            swallow: SwallowConfig::default(),
+            autostart: AutostartConfig::default(),
+        }
+    }
+}
+
+/// a keybind that launches a process.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BindConfig {
+    pub mods: Mod,
+    pub key: String,
+    pub command: Vec<String>,
+    /// spawn through `uwsm app` so the process gets its own systemd unit.
+    pub uwsm: bool,
+    pub group: String,
+    pub description: String,
+}
+
+/// create a keybind for a key as written in the config file: a single character or a keysym name.
+pub fn keybind(mods: Mod, key: &str) -> input::Keybind {
+    match key.chars().collect::<Vec<_>>()[..] {
+        [c] => input::keybind(mods, c),
+        _ => input::keybind(mods, key),
+    }
+}
+
+impl BindConfig {
+    /// register the keybind with the compositor.
+    pub fn register(&self) {
+        let command = self.command.clone();
+        let uwsm = self.uwsm;
+        keybind(self.mods, &self.key)
+            .on_press(move || {
+                let Some((program, args)) = command.split_first() else {
+                    return;
+                };
+                if uwsm {
+                    UwsmCommand::new(program).args(args).spawn();
+                } else {
+                    launch_context::spawn(Command::new(program).args(args));
+                }
+            })
+            .group(&self.group)
+            .description(&self.description);
+    }
+}
+
+/// global output settings, used for every output not covered by a matching profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputsConfig {
+    /// modes larger than this are never picked automatically.
+    pub max_mode: Size,
+    pub scale: f32,
+    pub vrr: output::Vrr,
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for OutputsConfig {
+    fn default() -> Self {
+        OutputsConfig {
+            // some outputs report a weird 4096x2160 mode that doesn't work well
+            max_mode: Size { w: 3840, h: 2160 },
+            scale: 2.0,
+            vrr: output::Vrr::OnDemand,
+            profiles: Vec::new(),
+        }
+    }
+}
+
+/// the layouts to cycle through, the layout each tag starts on and the gaps between windows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutsConfig {
+    pub cycle: Vec<LayoutKind>,
+    /// default layouts by tag name. tags not listed start on the first layout of the cycle.
+    pub tags: HashMap<String, LayoutKind>,
+    pub gaps: GapSizes,
+    /// gaps by output name, for outputs that shouldn't use the global ones.
+    pub output_gaps: HashMap<String, GapSizes>,
+    /// drop the gaps when a tag has a single window.
+    pub smart_gaps: bool,
+}
+
+impl Default for LayoutsConfig {
+    fn default() -> Self {
+        LayoutsConfig {
+            cycle: vec![
+                LayoutKind::MasterStack,
+                LayoutKind::CenteredMaster,
+                LayoutKind::Dwindle,
+                LayoutKind::Spiral,
+                LayoutKind::Corner,
+                LayoutKind::Fair,
+                LayoutKind::Monocle,
+            ],
+            tags: HashMap::new(),
+            gaps: GapSizes::default(),
+            output_gaps: HashMap::new(),
+            smart_gaps: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    InvalidSection { section: String, reason: String },
+    InvalidBind { chord: String, reason: String },
+    DuplicateBind(String),
+    BuiltinBind(String),
+    InvalidRule { rule: String, reason: String },
+    InvalidTags(String),
+    InvalidOutputs(String),
+    InvalidLayouts(String),
+    InvalidScratchpad { name: String, reason: String },
+    InvalidAttention { rule: String, reason: String },
+    InvalidTheme(String),
+    InvalidSwallow(String),
+    InvalidAutostart { name: String, reason: String },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => write!(f, "failed to read {}: {err}", path.display()),
+            ConfigError::Parse(path, err) => write!(f, "failed to parse {}: {err}", path.display()),
+            ConfigError::InvalidSection { section, reason } => {
+                write!(f, "section `{section}`: {reason}")
+            }
+            ConfigError::InvalidBind { chord, reason } => write!(f, "bind `{chord}`: {reason}"),
+            ConfigError::DuplicateBind(chord) => {
+                write!(f, "bind `{chord}` is defined more than once")
+            }
+            ConfigError::BuiltinBind(chord) => {
+                write!(f, "bind `{chord}` is already a built-in bind")
+            }
+            ConfigError::InvalidRule { rule, reason } => write!(f, "rule `{rule}`: {reason}"),
+            ConfigError::InvalidTags(reason) => write!(f, "tags: {reason}"),
+            ConfigError::InvalidOutputs(reason) => write!(f, "outputs: {reason}"),
+            ConfigError::InvalidLayouts(reason) => write!(f, "layouts: {reason}"),
+            ConfigError::InvalidScratchpad { name, reason } => {
+                write!(f, "scratchpad `{name}`: {reason}")
+            }
+            ConfigError::InvalidAttention { rule, reason } => {
+                write!(f, "attention rule `{rule}`: {reason}")
+            }
+            ConfigError::InvalidTheme(reason) => write!(f, "theme: {reason}"),
+            ConfigError::InvalidSwallow(reason) => write!(f, "swallow: {reason}"),
+            ConfigError::InvalidAutostart { name, reason } => {
+                write!(f, "autostart `{name}`: {reason}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    terminal: Option<String>,
+    #[serde(default)]
+    tags: RawTags,
+    #[serde(default)]
+    binds: toml::Table,
+    #[serde(default)]
+    rules: Vec<RawRule>,
+    #[serde(default)]
+    outputs: RawOutputs,
+    #[serde(default)]
+    layouts: RawLayouts,
+    #[serde(default)]
+    scratchpads: BTreeMap<String, RawScratchpad>,
+    #[serde(default)]
+    attention: Vec<RawAttentionRule>,
+    #[serde(default)]
+    theme: RawTheme,
+    #[serde(default)]
+    swallow: RawSwallow,
+    #[serde(default)]
+    autostart: RawAutostart,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTheme {
+    focused: Option<String>,
+    unfocused: Option<String>,
+    urgent: Option<String>,
+    sticky: Option<String>,
+    floating: Option<String>,
+    maximized: Option<String>,
+    thickness: Option<u32>,
+    smart_borders: Option<bool>,
+    #[serde(default)]
+    apps: HashMap<String, RawStyle>,
+}
+
+/// the per-app part of [`RawTheme`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawStyle {
+    focused: Option<String>,
+    unfocused: Option<String>,
+    urgent: Option<String>,
+    sticky: Option<String>,
+    floating: Option<String>,
+    maximized: Option<String>,
+    thickness: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAutostart {
+    #[serde(default)]
+    xdg: bool,
+    #[serde(default)]
+    apps: Vec<RawAutostartApp>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAutostartApp {
+    name: String,
+    #[serde(default)]
+    command: Vec<String>,
+    /// run the top-level `terminal` instead of `command`.
+    #[serde(default)]
+    terminal: bool,
+    #[serde(default = "default_true")]
+    uwsm: bool,
+    #[serde(default = "default_true")]
+    once: bool,
+    #[serde(default)]
+    unique: bool,
+    #[serde(default)]
+    after: Vec<String>,
+    #[serde(default)]
+    ready: RawReady,
+    output: Option<String>,
+    tag: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// at most one of `exit`, `socket` and `window`. `exit` is the grace period in seconds.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawReady {
+    exit: Option<f32>,
+    socket: Option<PathBuf>,
+    window: Option<String>,
+    /// seconds to wait for the socket or the window.
+    timeout: Option<f32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSwallow {
+    #[serde(default)]
+    terminals: Vec<RawTerminal>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTerminal {
+    app_id: Option<String>,
+    title_regex: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAttentionRule {
+    name: String,
+    app_id: Option<String>,
+    app_id_regex: Option<String>,
+    title_regex: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawScratchpad {
+    key: String,
+    command: Vec<String>,
+    app_id: Option<String>,
+    title_regex: Option<String>,
+    #[serde(default = "default_scratchpad_fraction")]
+    width: f32,
+    #[serde(default = "default_scratchpad_fraction")]
+    height: f32,
+}
+
+fn default_scratchpad_fraction() -> f32 {
+    0.6
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawLayouts {
+    cycle: Option<Vec<String>>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    gaps: RawGaps,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawGaps {
+    outer: Option<f32>,
+    inner: Option<f32>,
+    smart: Option<bool>,
+    #[serde(default)]
+    outputs: HashMap<String, RawGapSizes>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawGapSizes {
+    outer: f32,
+    inner: f32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTags {
+    names: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBind {
+    command: Vec<String>,
+    #[serde(default)]
+    uwsm: bool,
+    #[serde(default = "default_bind_group")]
+    group: String,
+    description: Option<String>,
+}
+
+fn default_bind_group() -> String {
+    "Process".to_owned()
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    name: String,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    fall_through: bool,
+
+    app_id: Option<String>,
+    app_id_regex: Option<String>,
+    title_regex: Option<String>,
+    class: Option<String>,
+    min_size: Option<RawSize>,
+    max_size: Option<RawSize>,
+    xwayland: Option<bool>,
+
+    tags: Option<Vec<String>>,
+    maximized: Option<bool>,
+    fullscreen: Option<bool>,
+    floating: Option<bool>,
+    geometry: Option<RawGeometry>,
+    output: Option<String>,
+    vrr: Option<RawVrrPolicy>,
+    decoration: Option<RawDecoration>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSize {
+    w: u32,
+    h: u32,
+}
+
+impl From<RawSize> for Size {
+    fn from(RawSize { w, h }: RawSize) -> Self {
+        Size { w, h }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawGeometry {
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RawVrrPolicy {
+    Always,
+    WhenFullscreen,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RawDecoration {
+    ServerSide,
+    ClientSide,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RawVrr {
+    Off,
+    AlwaysOn,
+    OnDemand,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawOutputs {
+    max_mode: Option<RawSize>,
+    scale: Option<f32>,
+    vrr: Option<RawVrr>,
+    #[serde(default)]
+    profiles: Vec<RawProfile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawProfile {
+    name: String,
+    outputs: Vec<RawProfileOutput>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawProfileOutput {
+    name: Option<String>,
+    make: Option<String>,
+    model: Option<String>,
+    serial: Option<String>,
+
+    mode: Option<String>,
+    scale: Option<f32>,
+    transform: Option<String>,
+    position: Option<RawPoint>,
+    vrr: Option<RawVrr>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPoint {
+    x: i32,
+    y: i32,
+}
+
+impl From<RawVrr> for output::Vrr {
+    fn from(vrr: RawVrr) -> Self {
+        match vrr {
+            RawVrr::Off => output::Vrr::Off,
+            RawVrr::AlwaysOn => output::Vrr::AlwaysOn,
+            RawVrr::OnDemand => output::Vrr::OnDemand,
+        }
+    }
+}
+
+/// the directory holding `pinnacle.toml`, using the same lookup order as pinnacle itself.
+pub fn config_dir() -> PathBuf {
+    [
+        env::var_os("PINNACLE_CONFIG_DIR").map(PathBuf::from),
+        env::var_os("XDG_CONFIG_HOME").map(|dir| PathBuf::from(dir).join("pinnacle")),
+        env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/pinnacle")),
+    ]
+    .into_iter()
+    .flatten()
+    .find(|dir| dir.join("pinnacle.toml").exists())
+    // pinnacle runs the config from the directory containing `pinnacle.toml`
+    .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// load the config file next to `pinnacle.toml`.
+///
+/// sections the file leaves out come from [`DEFAULT_CONFIG`], and so does a missing file. a section that
+/// doesn't parse falls back to the default one, and invalid entries within a section are skipped, all
+/// reported through the returned errors so that one typo doesn't take the rest of the config down with it.
+pub fn load(mod_key: Mod) -> (Config, Vec<ConfigError>) {
+    let path = config_dir().join(CONFIG_FILE_NAME);
+    let (sections, mut errors) = match fs::read_to_string(&path) {
+        Ok(contents) => read_sections(&path, &contents),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            tracing::info!(path = %path.display(), "no config file, using defaults");
+            (toml::Table::new(), Vec::new())
+        }
+        Err(err) => (toml::Table::new(), vec![ConfigError::Read(path, err)]),
+    };
+    let (config, mut parse_errors) = parse(with_defaults(sections, &mut errors), mod_key);
+    errors.append(&mut parse_errors);
+    (config, errors)
+}
+
+/// the top-level sections of the config file. when the file as a whole isn't valid TOML, each section is
+/// parsed on its own, and only the ones with syntax errors are dropped.
+fn read_sections(path: &Path, contents: &str) -> (toml::Table, Vec<ConfigError>) {
+    let err = match contents.parse::<toml::Table>() {
+        Ok(sections) => return (sections, Vec::new()),
+        Err(err) => err,
+    };
+    tracing::warn!(path = %path.display(), "failed to parse, reading the sections one by one: {err}");
+
+    // the section each line belongs to, with the top-level keys before the first header in ""
+    let mut section = "";
+    let line_sections = contents
+        .lines()
+        .map(|line| {
+            if let Some(name) = section_header(line) {
+                section = name;
+            }
+            section
+        })
+        .collect::<Vec<_>>();
+
+    let mut sections = toml::Table::new();
+    let mut errors = Vec::new();
+    let names = line_sections.iter().copied().collect::<BTreeSet<_>>();
+    for name in names {
+        // blank out the other sections rather than dropping them, so errors point at the right lines
+        let text = contents
+            .lines()
+            .zip(&line_sections)
+            .map(|(line, section)| if *section == name { line } else { "" })
+            .collect::<Vec<_>>()
+            .join("\n");
+        match text.parse::<toml::Table>() {
+            Ok(table) => sections.extend(table),
+            Err(err) => errors.push(ConfigError::Parse(path.to_owned(), err)),
+        }
+    }
+    (sections, errors)
+}
+
+/// the top-level key of a table header like `[binds."mod+p"]` or `[[rules]]`.
+fn section_header(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let header = line.strip_prefix("[[").or_else(|| line.strip_prefix('['))?;
+    let end = header.find(['.', ']'])?;
+    let name = header[..end].trim();
+    let bare = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    // arrays on lines of their own start with `[` too, but not with a bare key
+    (!name.is_empty() && name.chars().all(bare)).then_some(name)
+}
+
+/// the sections of the user's config, with the missing and invalid ones taken from [`DEFAULT_CONFIG`].
+fn with_defaults(sections: toml::Table, errors: &mut Vec<ConfigError>) -> RawConfig {
+    let mut merged = DEFAULT_CONFIG
+        .parse::<toml::Table>()
+        .expect("the default config is valid TOML");
+    for (name, value) in sections {
+        let section = toml::Table::from_iter([(name.clone(), value)]);
+        // each section is checked on its own, so a broken one doesn't hide the others
+        match RawConfig::deserialize(section.clone()) {
+            Ok(_) => merged.extend(section),
+            Err(err) => errors.push(ConfigError::InvalidSection {
+                section: name,
+                reason: err.message().to_owned(),
+            }),
+        }
+    }
+    RawConfig::deserialize(merged).unwrap_or_else(|err| {
+        tracing::error!("the default config is invalid: {err}");
+        RawConfig::default()
+    })
+}
+
+/// poll the config file for changes and call `on_change` with the new config whenever it changes. errors
+/// only go to the log, since the config is still applied in full on the next reload.
+pub async fn watch(mod_key: Mod, mut on_change: impl FnMut(Config)) {
+    let path = config_dir().join(CONFIG_FILE_NAME);
+    let modified = || fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+    let mut last = modified();
+    loop {
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        let current = modified();
+        if current == last {
+            continue;
+        }
+        last = current;
+        tracing::info!(path = %path.display(), "config file changed");
+        let (config, errors) = load(mod_key);
+        for err in errors {
+            tracing::error!("{err}");
+        }
+        on_change(config);
+    }
+}
+
+/// report config errors in the log and, with snowcap, on screen.
+pub fn report_errors(errors: &[ConfigError]) {
+    if errors.is_empty() {
+        return;
+    }
+    for err in errors {
+        tracing::error!("{err}");
+    }
+
+    #[cfg(feature = "snowcap")]
+    pinnacle_api::snowcap::ConfigCrashedMessage::new(format!(
+        "errors in {CONFIG_FILE_NAME}:\n{}",
+        errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+    .show();
+}
+
+fn parse(raw: RawConfig, mod_key: Mod) -> (Config, Vec<ConfigError>) {
+    let mut errors = Vec::new();
+    let defaults = Config::default();
+
+    let tags = match raw.tags.names {
+        Some(names) => match validate_tags(&names) {
+            Ok(()) => names,
+            Err(err) => {
+                errors.push(err);
+                defaults.tags
+            }
+        },
+        None => defaults.tags,
+    };
+
+    let builtin = builtin_chords(mod_key, tags.len());
+    let mut chords = HashSet::new();
+    let binds = raw
+        .binds
+        .into_iter()
+        .filter_map(|(chord, value)| {
+            let bind = parse_bind(&chord, value, mod_key)
+                .map_err(|reason| ConfigError::InvalidBind {
+                    chord: chord.clone(),
+                    reason,
+                })
+                .and_then(|bind| {
+                    let key = (bind.mods, bind.key.to_lowercase());
+                    if builtin.contains(&key) {
+                        Err(ConfigError::BuiltinBind(chord))
+                    } else if !chords.insert(key) {
+                        Err(ConfigError::DuplicateBind(chord))
+                    } else {
+                        Ok(bind)
+                    }
+                });
+            bind.map_err(|err| errors.push(err)).ok()
+        })
+        .collect();
+
+    let scratchpads = raw
+        .scratchpads
+        .into_iter()
+        .filter_map(|(name, raw)| {
+            let scratchpad = parse_scratchpad(&name, raw, mod_key)
+                .map_err(|reason| ConfigError::InvalidScratchpad {
+                    name: name.clone(),
+                    reason,
+                })
+                .and_then(|pad| {
+                    let key = (pad.mods, pad.key.to_lowercase());
+                    let reason = if builtin.contains(&key) {
+                        "its key is a built-in bind"
+                    } else if !chords.insert(key) {
+                        "its key is already bound"
+                    } else {
+                        return Ok(pad);
+                    };
+                    Err(ConfigError::InvalidScratchpad {
+                        name: name.clone(),
+                        reason: reason.to_owned(),
+                    })
+                });
+            scratchpad.map_err(|err| errors.push(err)).ok()
+        })
+        .collect();
+
+    let attention = raw
+        .attention
+        .into_iter()
+        .filter_map(|rule| {
+            let name = rule.name.clone();
+            parse_attention_rule(rule)
+                .map_err(|reason| errors.push(ConfigError::InvalidAttention { rule: name, reason }))
+                .ok()
+        })
+        .collect();
+
+    let theme = parse_theme(raw.theme).unwrap_or_else(|err| {
+        errors.push(err);
+        defaults.theme
+    });
+
+    let swallow = parse_swallow(raw.swallow).unwrap_or_else(|err| {
+        errors.push(err);
+        defaults.swallow
+    });
+
+    let terminal = raw.terminal.unwrap_or(defaults.terminal);
+    let autostart = parse_autostart(raw.autostart, &terminal, &mut errors);
+
+    let rules = RuleSet::new(raw.rules.into_iter().filter_map(|rule| {
+        let name = rule.name.clone();
+        parse_rule(rule)
+            .map_err(|reason| errors.push(ConfigError::InvalidRule { rule: name, reason }))
+            .ok()
+    }));
+
+    let layouts = parse_layouts(raw.layouts, &tags).unwrap_or_else(|err| {
+        errors.push(err);
+        defaults.layouts
+    });
+
+    let outputs = parse_outputs(raw.outputs).unwrap_or_else(|err| {
+        errors.push(err);
+        defaults.outputs
+    });
+
+    let config = Config {
+        terminal,
+        tags,
+        binds,
+        rules,
+        outputs,
+        layouts,
+        scratchpads,
+        attention,
+        theme,
+        swallow,
+        autostart,
+    };
+    (config, errors)
+}
+
+fn validate_tags(names: &[String]) -> Result<(), ConfigError> {
+    if names.is_empty() {
+        return Err(ConfigError::InvalidTags(
+            "at least one tag is required".to_owned(),
+        ));
+    }
+    if names.len() > 10 {
+        return Err(ConfigError::InvalidTags(
+            "at most 10 tags are supported, one per number key".to_owned(),
+        ));
+    }
+    if names.iter().any(|name| name == scratchpads::HIDDEN_TAG) {
+        return Err(ConfigError::InvalidTags(format!(
+            "`{}` is reserved for hidden scratchpads",
+            scratchpads::HIDDEN_TAG
+        )));
+    }
+    let mut seen = HashSet::new();
+    if let Some(dup) = names.iter().find(|name| !seen.insert(*name)) {
+        return Err(ConfigError::InvalidTags(format!(
+            "tag `{dup}` is listed twice"
+        )));
+    }
+    Ok(())
+}
+
+/// the chords of [`BUILTIN_BINDS`] and the per-tag binds `main.rs` sets up for `tag_count` tags, normalised
+/// the same way config chords are compared.
+fn builtin_chords(mod_key: Mod, tag_count: usize) -> HashSet<(Mod, String)> {
+    let tag_binds = ('1'..='9')
+        .chain('0'..='0')
+        .take(tag_count)
+        .flat_map(|index| {
+            [Mod::empty(), Mod::CTRL, Mod::SHIFT, Mod::CTRL | Mod::SHIFT]
+                .map(|mods| (mod_key | mods, index.to_string()))
+        });
+    BUILTIN_BINDS
+        .iter()
+        .map(|chord| {
+            let (mods, key) = parse_chord(chord, mod_key).expect("built-in chords are valid");
+            (mods, key.to_lowercase())
+        })
+        .chain(tag_binds)
+        .collect()
+}
+
+/// parse a chord like `mod+shift+p` or `mod4+Return`. `mod` is the configured mod key and `mod4` is super.
+fn parse_chord(chord: &str, mod_key: Mod) -> Result<(Mod, String), String> {
+    let mut parts = chord.split('+').map(str::trim).collect::<Vec<_>>();
+    let key = parts
+        .pop()
+        .filter(|key| !key.is_empty())
+        .ok_or("missing key")?;
+    let mods = parts.into_iter().try_fold(Mod::empty(), |mods, part| {
+        let m = match part.to_lowercase().as_str() {
+            "mod" => mod_key,
+            "mod4" | "super" => Mod::SUPER,
+            "alt" => Mod::ALT,
+            "ctrl" | "control" => Mod::CTRL,
+            "shift" => Mod::SHIFT,
+            other => return Err(format!("unknown modifier `{other}`")),
+        };
+        Ok(mods | m)
+    })?;
+    Ok((mods, key.to_owned()))
+}
+
+fn parse_bind(chord: &str, value: toml::Value, mod_key: Mod) -> Result<BindConfig, String> {
+    let (mods, key) = parse_chord(chord, mod_key)?;
+    let raw = RawBind::deserialize(value).map_err(|err| err.to_string())?;
+    let Some(program) = raw.command.first() else {
+        return Err("`command` must not be empty".to_owned());
+    };
+    Ok(BindConfig {
+        description: raw
+            .description
+            .unwrap_or_else(|| format!("spawn {program}")),
+        mods,
+        key,
+        command: raw.command,
+        uwsm: raw.uwsm,
+        group: raw.group,
+    })
+}
+
+fn parse_scratchpad(name: &str, raw: RawScratchpad, mod_key: Mod) -> Result<Scratchpad, String> {
+    let (mods, key) = parse_chord(&raw.key, mod_key)?;
+    if raw.command.is_empty() {
+        return Err("`command` must not be empty".to_owned());
+    }
+    let mut matchers = Vec::new();
+    if let Some(app_id) = raw.app_id {
+        matchers.push(Matcher::AppId(app_id));
+    }
+    if let Some(pattern) = raw.title_regex {
+        matchers.push(Matcher::TitleRegex(parse_regex("title_regex", &pattern)?));
+    }
+    if matchers.is_empty() {
+        return Err(
+            "a scratchpad needs an `app_id` or a `title_regex` to find its window".to_owned(),
+        );
+    }
+    for (field, fraction) in [("width", raw.width), ("height", raw.height)] {
+        if !(fraction > 0.0 && fraction <= 1.0) {
+            return Err(format!("`{field}` {fraction} must be in (0, 1]"));
+        }
+    }
+    Ok(Scratchpad {
+        name: name.to_owned(),
+        mods,
+        key,
+        command: raw.command,
+        matchers,
+        width: raw.width,
+        height: raw.height,
+    })
+}
+
+fn parse_attention_rule(raw: RawAttentionRule) -> Result<AttentionRule, String> {
+    let mut matchers = Vec::new();
+    if let Some(app_id) = raw.app_id {
+        matchers.push(Matcher::AppId(app_id));
+    }
+    if let Some(pattern) = raw.app_id_regex {
+        matchers.push(Matcher::AppIdRegex(parse_regex("app_id_regex", &pattern)?));
+    }
+    let Some(pattern) = raw.title_regex else {
+        return Err("`title_regex` is required, the title is what signals attention".to_owned());
+    };
+    matchers.push(Matcher::TitleRegex(parse_regex("title_regex", &pattern)?));
+    Ok(AttentionRule {
+        name: raw.name,
+        matchers,
+    })
+}
+
+fn parse_style(raw: RawStyle) -> Result<StyleOverride, String> {
+    let color = |field: &str, value: Option<String>| {
+        value
+            .map(|value| Rgba::from_str(&value).map_err(|err| format!("`{field}`: {err}")))
+            .transpose()
+    };
+    Ok(StyleOverride {
+        focused: color("focused", raw.focused)?,
+        unfocused: color("unfocused", raw.unfocused)?,
+        urgent: color("urgent", raw.urgent)?,
+        sticky: color("sticky", raw.sticky)?,
+        floating: color("floating", raw.floating)?,
+        maximized: color("maximized", raw.maximized)?,
+        thickness: raw.thickness,
+    })
+}
+
+fn parse_theme(raw: RawTheme) -> Result<Theme, ConfigError> {
+    let base = parse_style(RawStyle {
+        focused: raw.focused,
+        unfocused: raw.unfocused,
+        urgent: raw.urgent,
+        sticky: raw.sticky,
+        floating: raw.floating,
+        maximized: raw.maximized,
+        thickness: raw.thickness,
+    })
+    .map_err(ConfigError::InvalidTheme)?;
+    let apps = raw
+        .apps
+        .into_iter()
+        .map(|(app_id, style)| {
+            let style = parse_style(style)
+                .map_err(|reason| ConfigError::InvalidTheme(format!("app `{app_id}`: {reason}")))?;
+            Ok((app_id, style))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Theme {
+        border: BorderStyle::default().with(&base),
+        apps,
+        smart_borders: raw.smart_borders.unwrap_or(true),
+    })
+}
+
+fn parse_swallow(raw: RawSwallow) -> Result<SwallowConfig, ConfigError> {
+    let terminals = raw
+        .terminals
+        .into_iter()
+        .map(|terminal| {
+            let mut matchers = Vec::new();
+            if let Some(app_id) = terminal.app_id {
+                matchers.push(Matcher::AppId(app_id));
+            }
+            if let Some(pattern) = terminal.title_regex {
+                matchers.push(Matcher::TitleRegex(parse_regex("title_regex", &pattern)?));
+            }
+            if matchers.is_empty() {
+                return Err("a terminal needs an `app_id` or a `title_regex`".to_owned());
+            }
+            Ok(matchers)
+        })
+        .collect::<Result<_, _>>()
+        .map_err(ConfigError::InvalidSwallow)?;
+    Ok(SwallowConfig {
+        terminals,
+        exclude: raw.exclude,
+    })
+}
+
+/// a timeout or grace period in seconds.
+fn parse_seconds(field: &str, secs: f32) -> Result<Duration, String> {
+    Duration::try_from_secs_f32(secs)
+        .ok()
+        .filter(|duration| !duration.is_zero())
+        .ok_or_else(|| format!("`{field}` {secs} must be a positive number of seconds"))
+}
+
+fn parse_ready(raw: RawReady) -> Result<Readiness, String> {
+    let timeout = raw.timeout.map_or(Ok(Duration::from_secs(30)), |secs| {
+        parse_seconds("timeout", secs)
+    })?;
+    match (raw.exit, raw.socket, raw.window) {
+        (None, None, None) => Ok(Readiness::Spawned),
+        (Some(grace), None, None) => Ok(Readiness::Exit {
+            grace: parse_seconds("exit", grace)?,
+        }),
+        (None, Some(path), None) => Ok(Readiness::Socket {
+            // sockets usually live in the runtime dir, so relative paths start there
+            path: match env::var_os("XDG_RUNTIME_DIR") {
+                Some(dir) if path.is_relative() => PathBuf::from(dir).join(path),
+                _ => path,
+            },
+            timeout,
+        }),
+        (None, None, Some(app_id)) => Ok(Readiness::Window { app_id, timeout }),
+        _ => Err("`ready` takes only one of `exit`, `socket` and `window`".to_owned()),
+    }
+}
+
+fn parse_autostart_app(
+    raw: RawAutostartApp,
+    earlier: &[AutostartEntry],
+    terminal: &str,
+) -> Result<AutostartEntry, String> {
+    let command = match (raw.terminal, raw.command.is_empty()) {
+        (true, true) => vec![terminal.to_owned()],
+        (true, false) => return Err("`terminal` and `command` can't both be set".to_owned()),
+        (false, true) => return Err("`command` must not be empty".to_owned()),
+        (false, false) => raw.command,
+    };
+    if earlier.iter().any(|entry| entry.name == raw.name) {
+        return Err("there's already an entry with this name".to_owned());
+    }
+    // only depending on earlier entries rules out cycles
+    if let Some(dep) = raw
+        .after
+        .iter()
+        .find(|dep| !earlier.iter().any(|entry| entry.name == **dep))
+    {
+        return Err(format!(
+            "`after` names `{dep}`, which isn't an earlier entry"
+        ));
+    }
+    let ready = parse_ready(raw.ready)?;
+    if (raw.output.is_some() || raw.tag.is_some()) && !matches!(ready, Readiness::Window { .. }) {
+        return Err("`output` and `tag` need `ready.window` to recognize the window".to_owned());
+    }
+    Ok(AutostartEntry {
+        name: raw.name,
+        command,
+        uwsm: raw.uwsm,
+        once: raw.once,
+        unique: raw.unique,
+        after: raw.after,
+        ready,
+        output: raw.output,
+        tag: raw.tag,
+    })
+}
+
+fn parse_autostart(
+    raw: RawAutostart,
+    terminal: &str,
+    errors: &mut Vec<ConfigError>,
+) -> AutostartConfig {
+    let mut entries = Vec::new();
+    for app in raw.apps {
+        let name = app.name.clone();
+        match parse_autostart_app(app, &entries, terminal) {
+            Ok(entry) => entries.push(entry),
+            Err(reason) => errors.push(ConfigError::InvalidAutostart { name, reason }),
+        }
+    }
+    AutostartConfig {
+        entries,
+        xdg: raw.xdg,
+    }
+}
+
+fn parse_regex(field: &str, pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|err| format!("invalid `{field}`: {err}"))
+}
+
+fn parse_rule(raw: RawRule) -> Result<WindowRule, String> {
+    let mut matchers = Vec::new();
+    if let Some(app_id) = raw.app_id {
+        matchers.push(Matcher::AppId(app_id));
+    }
+    if let Some(pattern) = raw.app_id_regex {
+        matchers.push(Matcher::AppIdRegex(parse_regex("app_id_regex", &pattern)?));
+    }
+    if let Some(pattern) = raw.title_regex {
+        matchers.push(Matcher::TitleRegex(parse_regex("title_regex", &pattern)?));
+    }
+    if let Some(class) = raw.class {
+        matchers.push(Matcher::Class(class));
+    }
+    if raw.min_size.is_some() || raw.max_size.is_some() {
+        matchers.push(Matcher::InitialSize {
+            min: raw.min_size.map(Size::from),
+            max: raw.max_size.map(Size::from),
+        });
+    }
+    if let Some(xwayland) = raw.xwayland {
+        matchers.push(Matcher::XWayland(xwayland));
+    }
+    if matchers.is_empty() {
+        return Err("a rule needs at least one matcher".to_owned());
+    }
+
+    let mut actions = Vec::new();
+    if let Some(tags) = raw.tags {
+        actions.push(Action::Tags(tags));
+    }
+    if let Some(maximized) = raw.maximized {
+        actions.push(Action::Maximized(maximized));
+    }
+    if let Some(fullscreen) = raw.fullscreen {
+        actions.push(Action::Fullscreen(fullscreen));
+    }
+    if let Some(floating) = raw.floating {
+        actions.push(Action::Floating(floating));
+    }
+    if let Some(RawGeometry { x, y, w, h }) = raw.geometry {
+        actions.push(Action::Geometry(Geometry { x, y, w, h }));
+    }
+    if let Some(output) = raw.output {
+        actions.push(Action::Output(output));
+    }
+    if let Some(vrr) = raw.vrr {
+        actions.push(Action::Vrr(match vrr {
+            RawVrrPolicy::Always => VrrPolicy::Always,
+            RawVrrPolicy::WhenFullscreen => VrrPolicy::WhenFullscreen,
+            RawVrrPolicy::Never => VrrPolicy::Never,
+        }));
+    }
+    if let Some(decoration) = raw.decoration {
+        actions.push(Action::Decoration(match decoration {
+            RawDecoration::ServerSide => Decoration::ServerSide,
+            RawDecoration::ClientSide => Decoration::ClientSide,
+        }));
+    }
+
+    Ok(WindowRule {
+        name: raw.name,
+        priority: raw.priority,
+        matchers,
+        actions,
+        fall_through: raw.fall_through,
+    })
+}
+
+fn validate_scale(scale: f32) -> Result<f32, String> {
+    if scale > 0.0 && scale <= 10.0 {
+        Ok(scale)
+    } else {
+        Err(format!("scale {scale} must be in (0, 10]"))
+    }
+}
+
+fn parse_profile_output(raw: RawProfileOutput) -> Result<ProfileOutput, String> {
+    let matcher = OutputMatcher {
+        name: raw.name,
+        make: raw.make,
+        model: raw.model,
+        serial: raw.serial,
+    };
+    if matcher == OutputMatcher::default() {
+        return Err("an output needs at least one of name, make, model or serial".to_owned());
+    }
+    Ok(ProfileOutput {
+        matcher,
+        settings: OutputSettings {
+            mode: raw.mode.as_deref().map(ModeSpec::from_str).transpose()?,
+            scale: raw.scale.map(validate_scale).transpose()?,
+            transform: raw.transform.as_deref().map(parse_transform).transpose()?,
+            position: raw.position.map(|RawPoint { x, y }| Point { x, y }),
+            vrr: raw.vrr.map(output::Vrr::from),
+        },
+    })
+}
+
+fn parse_profile(raw: RawProfile) -> Result<Profile, String> {
+    if raw.outputs.is_empty() {
+        return Err("a profile needs at least one output".to_owned());
+    }
+    Ok(Profile {
+        name: raw.name,
+        outputs: raw
+            .outputs
+            .into_iter()
+            .map(parse_profile_output)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+fn parse_outputs(raw: RawOutputs) -> Result<OutputsConfig, ConfigError> {
+    let defaults = OutputsConfig::default();
+    let scale = raw
+        .scale
+        .map(validate_scale)
+        .transpose()
+        .map_err(ConfigError::InvalidOutputs)?
+        .unwrap_or(defaults.scale);
+    let profiles = raw
+        .profiles
+        .into_iter()
+        .map(|profile| {
+            let name = profile.name.clone();
+            parse_profile(profile).map_err(|reason| {
+                ConfigError::InvalidOutputs(format!("profile `{name}`: {reason}"))
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(OutputsConfig {
+        max_mode: raw.max_mode.map(Size::from).unwrap_or(defaults.max_mode),
+        scale,
+        vrr: raw.vrr.map(output::Vrr::from).unwrap_or(defaults.vrr),
+        profiles,
+    })
+}
+
+fn parse_layouts(raw: RawLayouts, tag_names: &[String]) -> Result<LayoutsConfig, ConfigError> {
+    let err = ConfigError::InvalidLayouts;
+    let cycle = match raw.cycle {
+        Some(names) => names
+            .iter()
+            .map(|name| LayoutKind::from_str(name))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err)?,
+        None => LayoutsConfig::default().cycle,
+    };
+    if cycle.is_empty() {
+        return Err(err("the cycle needs at least one layout".to_owned()));
+    }
+    let mut seen = HashSet::new();
+    if let Some(dup) = cycle.iter().find(|kind| !seen.insert(**kind)) {
+        return Err(err(format!("layout `{dup}` is in the cycle twice")));
+    }
+
+    let tags = raw
+        .tags
+        .into_iter()
+        .map(|(tag, name)| {
+            let kind =
+                LayoutKind::from_str(&name).map_err(|reason| format!("tag `{tag}`: {reason}"))?;
+            if !tag_names.contains(&tag) {
+                return Err(format!("tag `{tag}` doesn't exist"));
+            }
+            if !cycle.contains(&kind) {
+                return Err(format!("tag `{tag}`: layout `{kind}` is not in the cycle"));
+            }
+            Ok((tag, kind))
+        })
+        .collect::<Result<_, _>>()
+        .map_err(err)?;
+
+    let defaults = LayoutsConfig::default();
+    let gap = |what: &str, size: f32| {
+        if (0.0..=GapSizes::MAX).contains(&size) {
+            Ok(size)
+        } else {
+            Err(err(format!(
+                "{what} {size} must be in [0, {}]",
+                GapSizes::MAX
+            )))
+        }
+    };
+    let gaps = GapSizes {
+        outer: gap("outer gap", raw.gaps.outer.unwrap_or(defaults.gaps.outer))?,
+        inner: gap("inner gap", raw.gaps.inner.unwrap_or(defaults.gaps.inner))?,
+    };
+    let output_gaps = raw
+        .gaps
+        .outputs
+        .into_iter()
+        .map(|(output, RawGapSizes { outer, inner })| {
+            let gaps = GapSizes {
+                outer: gap(&format!("output `{output}`: outer gap"), outer)?,
+                inner: gap(&format!("output `{output}`: inner gap"), inner)?,
+            };
+            Ok((output, gaps))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(LayoutsConfig {
+        cycle,
+        tags,
+        gaps,
+        output_gaps,
+        smart_gaps: raw.gaps.smart.unwrap_or(defaults.smart_gaps),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_str(contents: &str) -> (Config, Vec<ConfigError>) {
+        let (sections, mut errors) = read_sections(Path::new(CONFIG_FILE_NAME), contents);
+        let (config, mut parse_errors) = parse(with_defaults(sections, &mut errors), Mod::ALT);
+        errors.append(&mut parse_errors);
+        (config, errors)
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        let (config, errors) = load_str("");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(!config.binds.is_empty());
+        assert!(!config.rules.rules().is_empty());
+        assert_eq!(config.autostart.entries[0].command, [config.terminal]);
+    }
+
+    #[test]
+    fn syntax_error_only_drops_its_section() {
+        let contents = r#"
+terminal = "foot"
+
+[theme]
+focused = 
+
+[binds]
+"mod+x" = { command = ["x"] }
+"#;
+        let (config, errors) = load_str(contents);
+        assert!(
+            matches!(&errors[..], [ConfigError::Parse(..)]),
+            "{errors:?}"
+        );
+        assert_eq!(config.terminal, "foot");
+        assert_eq!(config.binds.len(), 1);
+        assert_eq!(config.theme, load_str("").0.theme);
+    }
+
+    #[test]
+    fn binds_on_built_in_chords_are_rejected() {
+        let contents = r#"
+[binds]
+"mod+x" = { command = ["x"] }
+"mod+J" = { command = ["j"] }
+"alt+Tab" = { command = ["tab"] }
+"mod+shift+3" = { command = ["3"] }
+
+[scratchpads.pad]
+key = "mod+comma"
+command = ["pad"]
+app_id = "pad"
+"#;
+        let (config, errors) = load_str(contents);
+        let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "bind `alt+Tab` is already a built-in bind",
+                "bind `mod+J` is already a built-in bind",
+                "bind `mod+shift+3` is already a built-in bind",
+                "scratchpad `pad`: its key is a built-in bind",
+            ]
+        );
+        assert_eq!(config.binds.len(), 1);
+        assert!(config.scratchpads.is_empty());
+    }
+
+    #[test]
+    fn autostart_runs_the_configured_terminal() {
+        let (config, errors) = load_str("terminal = \"foot\"\n");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(config.autostart.entries[0].command, ["foot"]);
+    }
+
+    #[test]
+    fn invalid_section_falls_back_to_the_default() {
+        let (config, errors) = load_str("rules = 3\n[tags]\nnames = [\"a\"]\n");
+        assert!(
+            matches!(&errors[..], [ConfigError::InvalidSection { section, .. }] if section == "rules"),
+            "{errors:?}"
+        );
+        assert_eq!(config.tags, ["a"]);
+        assert!(!config.rules.rules().is_empty());
+    }
+
+    #[test]
+    fn section_headers() {
+        assert_eq!(section_header("[binds.\"mod+p\"]"), Some("binds"));
+        assert_eq!(section_header("  [[rules]] # comment"), Some("rules"));
+        assert_eq!(section_header("[layouts]"), Some("layouts"));
+        assert_eq!(section_header("[\"a\", \"b\"],"), None);
+        assert_eq!(section_header("terminal = \"foot\""), None);
+    }
+}
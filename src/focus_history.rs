@@ -0,0 +1,153 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use pinnacle_api::window;
+use pinnacle_api::window::WindowHandle;
+
+use crate::scratchpads;
+
+/// how long after the last step an MRU cycle is considered over. pinnacle can't report the release of the
+/// mod key without binding it, which would swallow it from clients, so repeated presses within this
+/// window stand in for holding the modifier.
+const CYCLE_TIMEOUT: Duration = Duration::from_millis(1200);
+
+#[derive(Debug, Clone, Copy)]
+struct Cycle {
+    /// the position in the history of the window the cycle is on.
+    index: usize,
+    last_step: Instant,
+}
+
+#[derive(Debug, Default)]
+struct History {
+    /// most recently focused first.
+    windows: Vec<WindowHandle>,
+    cycle: Option<Cycle>,
+}
+
+impl History {
+    /// end the cycle, moving the window it ended on to the front as if it was focused normally.
+    fn end_cycle(&mut self) {
+        if let Some(cycle) = self.cycle.take()
+            && cycle.index < self.windows.len()
+        {
+            let window = self.windows.remove(cycle.index);
+            self.windows.insert(0, window);
+        }
+    }
+
+    /// end the cycle if it has expired.
+    fn finish_cycle(&mut self) {
+        if self
+            .cycle
+            .is_some_and(|cycle| cycle.last_step.elapsed() >= CYCLE_TIMEOUT)
+        {
+            self.end_cycle();
+        }
+    }
+
+    /// whether `window` is the one an ongoing cycle is on, so that its focus came from the cycle.
+    fn is_cycling_to(&self, window: &WindowHandle) -> bool {
+        self.cycle
+            .is_some_and(|cycle| self.windows.get(cycle.index) == Some(window))
+    }
+
+    fn prune(&mut self) {
+        let alive = window::get_all().collect::<Vec<_>>();
+        self.windows.retain(|window| alive.contains(window));
+    }
+}
+
+/// the most recently used order of windows, fed from `WindowSignal::Focused`.
+#[derive(Debug, Default)]
+pub struct FocusHistory {
+    history: Mutex<History>,
+}
+
+impl FocusHistory {
+    /// record that `window` got focus. the focus an ongoing MRU cycle just gave its window doesn't reorder
+    /// the history, so that the cycle can keep walking back. any other focus change ends the cycle.
+    pub fn focused(&self, window: &WindowHandle) {
+        let mut history = self.history.lock().unwrap();
+        history.finish_cycle();
+        if history.is_cycling_to(window) {
+            return;
+        }
+        history.end_cycle();
+        history.windows.retain(|w| w != window);
+        history.windows.insert(0, window.clone());
+    }
+
+    pub fn forget(&self, window: &WindowHandle) {
+        let mut history = self.history.lock().unwrap();
+        if let Some(idx) = history.windows.iter().position(|w| w == window) {
+            history.windows.remove(idx);
+            if let Some(cycle) = &mut history.cycle
+                && cycle.index >= idx
+            {
+                cycle.index = cycle.index.saturating_sub(1);
+            }
+        }
+    }
+
+    /// jump back to the window focused before the current one.
+    pub fn previous(&self) {
+        let target = {
+            let mut history = self.history.lock().unwrap();
+            history.finish_cycle();
+            history.prune();
+            history
+                .windows
+                .iter()
+                .skip(1)
+                .find(|window| !scratchpads::is_hidden(window))
+                .cloned()
+        };
+        if let Some(window) = target {
+            jump_to(&window);
+        }
+    }
+
+    /// take one step through the history: further back with `step = 1`, forward again with `step = -1`.
+    /// a new cycle starts from the most recently used window.
+    pub fn cycle(&self, step: isize) {
+        let target = {
+            let mut history = self.history.lock().unwrap();
+            history.finish_cycle();
+            history.prune();
+            let len = history.windows.len();
+            if len < 2 {
+                return;
+            }
+            let mut index = history.cycle.map_or(0, |cycle| cycle.index);
+            // hidden scratchpads can't be jumped to without showing their tag, so skip over them
+            for _ in 0..len {
+                index = (index as isize + step).rem_euclid(len as isize) as usize;
+                if !scratchpads::is_hidden(&history.windows[index]) {
+                    break;
+                }
+            }
+            history.cycle = Some(Cycle {
+                index,
+                last_step: Instant::now(),
+            });
+            history.windows[index].clone()
+        };
+        jump_to(&target);
+    }
+}
+
+/// focus `window`, switching to one of its tags first if it isn't on an active one.
+pub fn jump_to(window: &WindowHandle) {
+    if !window.is_on_active_tag()
+        && let Some(tag) = window.tags().next()
+    {
+        tag.switch_to();
+    }
+    if let Some(output) = window.output() {
+        output.focus();
+    }
+    window.raise();
+    window.set_focused(true);
+}
@@ -0,0 +1,118 @@
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use pinnacle_api::output;
+use pinnacle_api::process::Child;
+use pinnacle_api::process::Command;
+use pinnacle_api::tag;
+use pinnacle_api::window::WindowHandle;
+
+use crate::process_tree;
+
+/// the environment variable a launch's token is passed in. it survives where the process tree doesn't,
+/// like for apps that `uwsm app` starts in their own systemd unit.
+pub const TOKEN_VAR: &str = "PINNACLE_LAUNCH_TOKEN";
+
+/// how long a launch is remembered. windows that take longer to map go wherever the rules put them.
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// where the user was when they launched something.
+#[derive(Debug)]
+struct Launch {
+    token: String,
+    /// the spawned process, when it was spawned directly.
+    pid: Option<u32>,
+    output: String,
+    tags: Vec<String>,
+    at: Instant,
+}
+
+// spawning happens all over the config, from binds, scratchpads and autostart, so the launches are kept
+// in one place rather than threaded through every caller
+static LAUNCHES: Mutex<Vec<Launch>> = Mutex::new(Vec::new());
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+fn launches() -> std::sync::MutexGuard<'static, Vec<Launch>> {
+    let mut launches = LAUNCHES.lock().unwrap();
+    launches.retain(|launch| launch.at.elapsed() < LAUNCH_TIMEOUT);
+    launches
+}
+
+/// remember the focused output and its active tags for a launch that's about to happen, and return the
+/// token to pass to the launched process.
+pub fn begin() -> Option<String> {
+    let output = output::get_focused()?;
+    // the config process is part of the token, so tokens from before a reload don't match
+    let token = format!(
+        "{}-{}",
+        std::process::id(),
+        NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+    );
+    launches().push(Launch {
+        token: token.clone(),
+        pid: None,
+        output: output.name(),
+        tags: output.active_tags().map(|tag| tag.name()).collect(),
+        at: Instant::now(),
+    });
+    Some(token)
+}
+
+/// record the process spawned for the launch with `token`.
+pub fn spawned(token: &str, child: Option<&Child>) {
+    if let Some(child) = child
+        && let Some(launch) = launches().iter_mut().find(|launch| launch.token == token)
+    {
+        launch.pid = Some(child.pid());
+    }
+}
+
+/// spawn `command`, remembering where it was launched from.
+pub fn spawn(command: &mut Command) -> Option<Child> {
+    let Some(token) = begin() else {
+        return command.spawn();
+    };
+    let child = command.env(TOKEN_VAR, &token).spawn();
+    spawned(&token, child.as_ref());
+    child
+}
+
+/// put `window` on the output and tags it was launched from, if it came from a launch of this config.
+/// returns whether it did.
+pub fn place(window: &WindowHandle) -> bool {
+    let Some(pid) = window.pid() else {
+        return false;
+    };
+    let (output_name, tag_names) = {
+        let launches = launches();
+        let Some(launch) = std::iter::once(pid)
+            .chain(process_tree::ancestors(pid))
+            .find_map(|pid| {
+                let token = process_tree::env_var(pid, TOKEN_VAR);
+                launches
+                    .iter()
+                    .find(|launch| launch.pid == Some(pid) || token.as_ref() == Some(&launch.token))
+            })
+        else {
+            return false;
+        };
+        (launch.output.clone(), launch.tags.clone())
+    };
+
+    let Some(output) = output::get_by_name(&output_name) else {
+        return false;
+    };
+    let tags = tag_names
+        .iter()
+        .filter_map(|name| tag::get_on_output(name, &output))
+        .collect::<Vec<_>>();
+    if tags.is_empty() {
+        return false;
+    }
+    tracing::debug!(app_id = %window.app_id(), output = %output_name, tags = ?tag_names, "placing window where it was launched");
+    window.set_tags(tags);
+    true
+}
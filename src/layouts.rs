@@ -0,0 +1,596 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use pinnacle_api::layout::Gaps;
+use pinnacle_api::layout::LayoutGenerator;
+use pinnacle_api::layout::LayoutNode;
+use pinnacle_api::layout::generators::Corner;
+use pinnacle_api::layout::generators::Cycle;
+use pinnacle_api::layout::generators::Dwindle;
+use pinnacle_api::layout::generators::Fair;
+use pinnacle_api::layout::generators::MasterStack;
+use pinnacle_api::layout::generators::Spiral;
+use pinnacle_api::tag::TagHandle;
+use pinnacle_api::window;
+use pinnacle_api::window::LayoutMode;
+use pinnacle_api::window::WindowHandle;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config_file::LayoutsConfig;
+use crate::state;
+
+mod centered_master;
+
+pub use centered_master::CenteredMaster;
+
+pub fn into_box<'a, T: LayoutGenerator + Send + 'a>(
+    generator: T,
+) -> Box<dyn LayoutGenerator + Send + 'a> {
+    Box::new(generator) as _
+}
+
+/// the layouts that can be put in the cycle, by name.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum LayoutKind {
+    MasterStack,
+    CenteredMaster,
+    Dwindle,
+    Spiral,
+    Corner,
+    Fair,
+    Monocle,
+}
+
+impl LayoutKind {
+    /// whether the layout has a master area that [`MasterParams`] apply to.
+    pub fn has_master(self) -> bool {
+        matches!(self, LayoutKind::MasterStack | LayoutKind::CenteredMaster)
+    }
+
+    pub fn generator(self) -> Box<dyn LayoutGenerator + Send> {
+        self.generator_with(GapSizes::default(), MasterParams::default(), 0)
+    }
+
+    /// the generator for this layout with the given gaps and, for layouts that have one, master area.
+    /// `focused` is the position of the focused window among the tiled ones, for layouts that only show
+    /// that window.
+    pub fn generator_with(
+        self,
+        gaps: GapSizes,
+        master: MasterParams,
+        focused: u32,
+    ) -> Box<dyn LayoutGenerator + Send> {
+        let outer_gaps = Gaps::from(gaps.outer);
+        let inner_gaps = Gaps::from(gaps.inner);
+        let MasterParams {
+            master_factor,
+            master_count,
+        } = master;
+        match self {
+            LayoutKind::MasterStack => into_box(MasterStack {
+                outer_gaps,
+                inner_gaps,
+                master_factor,
+                master_count,
+                ..Default::default()
+            }),
+            LayoutKind::CenteredMaster => into_box(CenteredMaster {
+                outer_gaps,
+                inner_gaps,
+                master_factor,
+                master_count,
+            }),
+            LayoutKind::Dwindle => into_box(Dwindle {
+                outer_gaps,
+                inner_gaps,
+            }),
+            LayoutKind::Spiral => into_box(Spiral {
+                outer_gaps,
+                inner_gaps,
+            }),
+            LayoutKind::Corner => into_box(Corner {
+                outer_gaps,
+                inner_gaps,
+                ..Default::default()
+            }),
+            LayoutKind::Fair => into_box(Fair {
+                outer_gaps,
+                inner_gaps,
+                ..Default::default()
+            }),
+            LayoutKind::Monocle => into_box(Monocle {
+                gaps: outer_gaps,
+                focused,
+            }),
+        }
+    }
+}
+
+impl Display for LayoutKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LayoutKind::MasterStack => "master-stack",
+            LayoutKind::CenteredMaster => "centered-master",
+            LayoutKind::Dwindle => "dwindle",
+            LayoutKind::Spiral => "spiral",
+            LayoutKind::Corner => "corner",
+            LayoutKind::Fair => "fair",
+            LayoutKind::Monocle => "monocle",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for LayoutKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "master-stack" => LayoutKind::MasterStack,
+            "centered-master" => LayoutKind::CenteredMaster,
+            "dwindle" => LayoutKind::Dwindle,
+            "spiral" => LayoutKind::Spiral,
+            "corner" => LayoutKind::Corner,
+            "fair" => LayoutKind::Fair,
+            "monocle" => LayoutKind::Monocle,
+            other => return Err(format!("unknown layout `{other}`")),
+        })
+    }
+}
+
+/// a layout showing only the focused window, using the whole output. the other windows are given no
+/// space, so focusing a window is how it's brought into view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monocle {
+    pub gaps: Gaps,
+    /// the position of the focused window among the tiled ones. the first window is shown when it's out
+    /// of range.
+    pub focused: u32,
+}
+
+impl Default for Monocle {
+    fn default() -> Self {
+        Monocle {
+            gaps: Gaps::from(4.0),
+            focused: 0,
+        }
+    }
+}
+
+impl LayoutGenerator for Monocle {
+    fn layout(&self, window_count: u32) -> LayoutNode {
+        let focused = if self.focused < window_count {
+            self.focused
+        } else {
+            0
+        };
+        let root = LayoutNode::new();
+        root.set_gaps(self.gaps);
+        for idx in 0..window_count {
+            let node = LayoutNode::new();
+            node.set_size_proportion(if idx == focused { 1.0 } else { 0.0 });
+            root.add_child(node);
+        }
+        root
+    }
+}
+
+/// the position of `window` among the windows tiled on the active tags of its output, which is the order
+/// layouts get them in. `None` if the window isn't tiled there.
+pub fn tiled_position(window: &WindowHandle) -> Option<u32> {
+    let output = window.output()?;
+    let tiled = |window: &WindowHandle| {
+        window.output().as_ref() == Some(&output)
+            && window.is_on_active_tag()
+            && window.layout_mode() == LayoutMode::Tiled
+    };
+    if !tiled(window) {
+        return None;
+    }
+    let position = window::get_all().filter(tiled).position(|w| w == *window)?;
+    Some(position as u32)
+}
+
+/// the gaps around (outer) and between (inner) windows, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GapSizes {
+    pub outer: f32,
+    pub inner: f32,
+}
+
+impl GapSizes {
+    pub const NONE: GapSizes = GapSizes {
+        outer: 0.0,
+        inner: 0.0,
+    };
+    pub const MAX: f32 = 64.0;
+
+    /// grow or shrink both gaps by `delta`, keeping them within `0..=MAX`.
+    pub fn adjusted(self, delta: f32) -> GapSizes {
+        GapSizes {
+            outer: (self.outer + delta).clamp(0.0, Self::MAX),
+            inner: (self.inner + delta).clamp(0.0, Self::MAX),
+        }
+    }
+}
+
+impl Default for GapSizes {
+    fn default() -> Self {
+        GapSizes {
+            outer: 4.0,
+            inner: 4.0,
+        }
+    }
+}
+
+/// wraps a layout to drop all gaps when there's a single window, so a lone window gets the whole output.
+/// `generator` builds the wrapped layout for the gaps to use.
+pub struct SmartGaps<F> {
+    pub gaps: GapSizes,
+    pub generator: F,
+}
+
+impl<F, G> LayoutGenerator for SmartGaps<F>
+where
+    F: Fn(GapSizes) -> G,
+    G: LayoutGenerator,
+{
+    fn layout(&self, window_count: u32) -> LayoutNode {
+        let gaps = if window_count <= 1 {
+            GapSizes::NONE
+        } else {
+            self.gaps
+        };
+        (self.generator)(gaps).layout(window_count)
+    }
+}
+
+const STATE_FILE: &str = "layouts.toml";
+
+/// the size of the master area of a tag, for layouts that have one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MasterParams {
+    pub master_factor: f32,
+    pub master_count: u32,
+}
+
+impl MasterParams {
+    pub const MIN_MASTER_FACTOR: f32 = 0.1;
+    pub const MAX_MASTER_FACTOR: f32 = 0.9;
+    pub const MAX_MASTER_COUNT: u32 = 8;
+
+    /// keep the parameters within usable bounds.
+    pub fn clamped(self) -> Self {
+        MasterParams {
+            master_factor: self
+                .master_factor
+                .clamp(Self::MIN_MASTER_FACTOR, Self::MAX_MASTER_FACTOR),
+            master_count: self.master_count.clamp(1, Self::MAX_MASTER_COUNT),
+        }
+    }
+}
+
+impl Default for MasterParams {
+    fn default() -> Self {
+        MasterParams {
+            master_factor: 0.5,
+            master_count: 1,
+        }
+    }
+}
+
+/// the layout state of a single tag, as stored on disk.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedTagLayout {
+    pub layout: String,
+    pub master_factor: Option<f32>,
+    pub master_count: Option<u32>,
+    pub outer_gaps: Option<f32>,
+    pub inner_gaps: Option<f32>,
+}
+
+/// per-tag layout state, keyed by output name and then tag name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedLayouts {
+    #[serde(default)]
+    pub outputs: BTreeMap<String, BTreeMap<String, SavedTagLayout>>,
+}
+
+impl SavedLayouts {
+    /// write the layout state to disk so it survives config reloads.
+    pub fn write(&self) {
+        state::save(STATE_FILE, self);
+    }
+}
+
+/// a [`Cycle`] over the configured layouts that also knows which layout each tag is on, so tags can start
+/// out on a layout other than the first and keep their layout across config reloads.
+pub struct Layouts {
+    cycle: Cycle<Box<dyn LayoutGenerator + Send>>,
+    kinds: Vec<LayoutKind>,
+    /// default layouts by tag name.
+    defaults: HashMap<String, LayoutKind>,
+    indices: HashMap<TagHandle, usize>,
+    /// master parameters for tags whose master area was changed from the default.
+    master_params: HashMap<TagHandle, MasterParams>,
+    /// gaps for tags whose gaps were changed from their output's.
+    tag_gaps: HashMap<TagHandle, GapSizes>,
+    /// the name of the output each tag is on, to find its gaps.
+    tag_outputs: HashMap<TagHandle, String>,
+    /// tag names, so saving the layout state doesn't have to ask the compositor.
+    tag_names: HashMap<TagHandle, String>,
+    gaps: GapSizes,
+    output_gaps: HashMap<String, GapSizes>,
+    smart_gaps: bool,
+    current_tag: Option<TagHandle>,
+    /// the position of the focused window among the tiled windows of each tag, for [`Monocle`].
+    focused: HashMap<TagHandle, u32>,
+    saved: SavedLayouts,
+    /// whether `saved` changed since it was last taken to be written to disk.
+    unsaved: bool,
+}
+
+impl Layouts {
+    pub fn new(config: LayoutsConfig) -> Layouts {
+        Layouts {
+            cycle: Cycle::new(config.cycle.iter().map(|kind| kind.generator())),
+            kinds: config.cycle,
+            defaults: config.tags,
+            indices: HashMap::new(),
+            master_params: HashMap::new(),
+            tag_gaps: HashMap::new(),
+            tag_outputs: HashMap::new(),
+            tag_names: HashMap::new(),
+            gaps: config.gaps,
+            output_gaps: config.output_gaps,
+            smart_gaps: config.smart_gaps,
+            current_tag: None,
+            focused: HashMap::new(),
+            saved: state::load(STATE_FILE),
+            unsaved: false,
+        }
+    }
+
+    pub fn set_current_tag(&mut self, tag: TagHandle) {
+        self.cycle.set_current_tag(tag.clone());
+        self.current_tag = Some(tag);
+    }
+
+    pub fn current_tree_id(&self) -> u32 {
+        self.cycle.current_tree_id()
+    }
+
+    pub fn layout(&self, window_count: u32) -> LayoutNode {
+        let Some(tag) = &self.current_tag else {
+            return self.cycle.layout(window_count);
+        };
+        // the generators in the cycle are shared between tags, so per-tag parameters need a generator
+        // built for the tag
+        let kind = self.current_kind(tag);
+        let master = self.master_params.get(tag).copied().unwrap_or_default();
+        let gaps = self.gaps(tag);
+        let focused = self.focused.get(tag).copied().unwrap_or_default();
+        if self.smart_gaps {
+            SmartGaps {
+                gaps,
+                generator: |gaps| kind.generator_with(gaps, master, focused),
+            }
+            .layout(window_count)
+        } else {
+            kind.generator_with(gaps, master, focused)
+                .layout(window_count)
+        }
+    }
+
+    /// the gaps of `tag`: its own if they were changed, else its output's, else the global ones.
+    pub fn gaps(&self, tag: &TagHandle) -> GapSizes {
+        self.tag_gaps.get(tag).copied().unwrap_or_else(|| {
+            self.tag_outputs
+                .get(tag)
+                .and_then(|output| self.output_gaps.get(output))
+                .copied()
+                .unwrap_or(self.gaps)
+        })
+    }
+
+    /// grow or shrink the gaps of `tag` by `delta`. returns whether they changed.
+    pub fn adjust_gaps(&mut self, tag: &TagHandle, delta: f32) -> bool {
+        let current = self.gaps(tag);
+        let gaps = current.adjusted(delta);
+        if gaps == current {
+            return false;
+        }
+        self.tag_gaps.insert(tag.clone(), gaps);
+        self.persist(tag);
+        true
+    }
+
+    /// remember that the window at `position` among the tiled windows of `tag` is focused.
+    pub fn set_focused(&mut self, tag: TagHandle, position: u32) {
+        self.focused.insert(tag, position);
+    }
+
+    pub fn current_kind(&self, tag: &TagHandle) -> LayoutKind {
+        self.kinds[self.indices.get(tag).copied().unwrap_or_default()]
+    }
+
+    fn step(&mut self, tag: &TagHandle, forward: bool) {
+        let len = self.kinds.len();
+        let idx = self.indices.entry(tag.clone()).or_default();
+        if forward {
+            self.cycle.cycle_layout_forward(tag);
+            *idx = (*idx + 1) % len;
+        } else {
+            self.cycle.cycle_layout_backward(tag);
+            *idx = (*idx + len - 1) % len;
+        }
+    }
+
+    pub fn cycle_layout_forward(&mut self, tag: &TagHandle) {
+        self.step(tag, true);
+        self.persist(tag);
+    }
+
+    pub fn cycle_layout_backward(&mut self, tag: &TagHandle) {
+        self.step(tag, false);
+        self.persist(tag);
+    }
+
+    /// apply `f` to the master parameters of `tag`. returns whether the tag's current layout has a master
+    /// area to adjust.
+    fn update_master(
+        &mut self,
+        tag: &TagHandle,
+        f: impl FnOnce(MasterParams) -> MasterParams,
+    ) -> bool {
+        if !self.current_kind(tag).has_master() {
+            return false;
+        }
+        let params = self.master_params.entry(tag.clone()).or_default();
+        *params = f(*params).clamped();
+        self.persist(tag);
+        true
+    }
+
+    /// grow or shrink the master area of `tag` by `delta`.
+    pub fn adjust_master_factor(&mut self, tag: &TagHandle, delta: f32) -> bool {
+        self.update_master(tag, |params| MasterParams {
+            master_factor: params.master_factor + delta,
+            ..params
+        })
+    }
+
+    /// add or remove windows from the master area of `tag`.
+    pub fn adjust_master_count(&mut self, tag: &TagHandle, delta: i32) -> bool {
+        self.update_master(tag, |params| MasterParams {
+            master_count: params.master_count.saturating_add_signed(delta),
+            ..params
+        })
+    }
+
+    /// put the master area of `tag` back to its default size.
+    pub fn reset_master(&mut self, tag: &TagHandle) -> bool {
+        self.update_master(tag, |_| MasterParams::default())
+    }
+
+    /// switch `tag` to the given layout. does nothing if the layout isn't in the cycle.
+    fn set_layout(&mut self, tag: &TagHandle, kind: LayoutKind) {
+        let Some(target) = self.kinds.iter().position(|k| *k == kind) else {
+            tracing::warn!(layout = %kind, "layout is not in the cycle");
+            return;
+        };
+        while self.indices.get(tag).copied().unwrap_or_default() != target {
+            self.step(tag, true);
+        }
+    }
+
+    /// put the newly created `tags`, given with their names, on the layout they had before the last
+    /// reload, or on their configured default layout.
+    pub fn restore(&mut self, output_name: &str, tags: &[(TagHandle, String)]) {
+        for (tag, tag_name) in tags {
+            let saved = self
+                .saved
+                .outputs
+                .get(output_name)
+                .and_then(|tags| tags.get(tag_name))
+                .cloned();
+
+            let saved_kind = saved
+                .as_ref()
+                .and_then(|saved| saved.layout.parse::<LayoutKind>().ok())
+                .filter(|kind| self.kinds.contains(kind));
+            if let Some(kind) = saved_kind.or_else(|| self.defaults.get(tag_name).copied()) {
+                self.set_layout(tag, kind);
+            }
+            if let Some(saved) = &saved
+                && (saved.master_factor.is_some() || saved.master_count.is_some())
+            {
+                let defaults = MasterParams::default();
+                let params = MasterParams {
+                    master_factor: saved.master_factor.unwrap_or(defaults.master_factor),
+                    master_count: saved.master_count.unwrap_or(defaults.master_count),
+                };
+                self.master_params.insert(tag.clone(), params.clamped());
+            }
+            self.tag_outputs.insert(tag.clone(), output_name.to_owned());
+            self.tag_names.insert(tag.clone(), tag_name.clone());
+            if let Some(saved) = &saved
+                && let (Some(outer), Some(inner)) = (saved.outer_gaps, saved.inner_gaps)
+            {
+                self.tag_gaps
+                    .insert(tag.clone(), GapSizes { outer, inner }.adjusted(0.0));
+            }
+        }
+    }
+
+    /// remember the layout state of `tag` to be saved. writing it is left to whoever holds the lock on
+    /// the layouts, through [`Layouts::take_unsaved`], so layout requests don't wait on the disk.
+    fn persist(&mut self, tag: &TagHandle) {
+        let (Some(output_name), Some(tag_name)) =
+            (self.tag_outputs.get(tag), self.tag_names.get(tag))
+        else {
+            tracing::warn!("not saving the layout of a tag that was never restored");
+            return;
+        };
+        let params = self.master_params.get(tag);
+        let gaps = self.tag_gaps.get(tag);
+        let saved = SavedTagLayout {
+            layout: self.current_kind(tag).to_string(),
+            master_factor: params.map(|params| params.master_factor),
+            master_count: params.map(|params| params.master_count),
+            outer_gaps: gaps.map(|gaps| gaps.outer),
+            inner_gaps: gaps.map(|gaps| gaps.inner),
+        };
+        self.saved
+            .outputs
+            .entry(output_name.clone())
+            .or_default()
+            .insert(tag_name.clone(), saved);
+        self.unsaved = true;
+    }
+
+    /// the layout state to write to disk, if it changed since this was last called. write it with
+    /// [`SavedLayouts::write`] after letting go of the lock.
+    pub fn take_unsaved(&mut self) -> Option<SavedLayouts> {
+        std::mem::take(&mut self.unsaved).then(|| self.saved.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a monocle tree with one node per proportion. `LayoutNode` is opaque, so trees are compared through
+    /// their debug output.
+    fn monocle_tree(proportions: &[f32]) -> String {
+        let root = LayoutNode::new();
+        root.set_gaps(Gaps::from(4.0));
+        for &proportion in proportions {
+            let node = LayoutNode::new();
+            node.set_size_proportion(proportion);
+            root.add_child(node);
+        }
+        format!("{root:?}")
+    }
+
+    #[test]
+    fn monocle_gives_the_focused_window_the_output() {
+        let layout = |focused, window_count| {
+            let monocle = Monocle {
+                focused,
+                ..Default::default()
+            };
+            format!("{:?}", monocle.layout(window_count))
+        };
+        assert_eq!(layout(0, 0), monocle_tree(&[]));
+        assert_eq!(layout(0, 1), monocle_tree(&[1.0]));
+        assert_eq!(layout(0, 3), monocle_tree(&[1.0, 0.0, 0.0]));
+        assert_eq!(layout(2, 4), monocle_tree(&[0.0, 0.0, 1.0, 0.0]));
+        assert_eq!(layout(3, 4), monocle_tree(&[0.0, 0.0, 0.0, 1.0]));
+        // the focused window went away before the layout caught up
+        assert_eq!(layout(4, 4), monocle_tree(&[1.0, 0.0, 0.0, 0.0]));
+    }
+}
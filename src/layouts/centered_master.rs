@@ -0,0 +1,229 @@
+use pinnacle_api::layout::Gaps;
+use pinnacle_api::layout::LayoutDir;
+use pinnacle_api::layout::LayoutGenerator;
+use pinnacle_api::layout::LayoutNode;
+
+use crate::layouts::MasterParams;
+
+/// a master column in the middle of the output with stacks on both sides, for ultrawide monitors.
+///
+/// windows fill the master column first, then the right stack, then the left one. the stacks are split
+/// evenly, with the right stack taking the extra window when the count is odd, so the left stack only
+/// gets windows once there are at least three.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CenteredMaster {
+    pub outer_gaps: Gaps,
+    pub inner_gaps: Gaps,
+    /// the share of the output's width taken by the master column.
+    pub master_factor: f32,
+    pub master_count: u32,
+}
+
+impl Default for CenteredMaster {
+    fn default() -> Self {
+        CenteredMaster {
+            outer_gaps: Gaps::from(4.0),
+            inner_gaps: Gaps::from(4.0),
+            master_factor: 0.5,
+            master_count: 1,
+        }
+    }
+}
+
+/// the number of windows in each column.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Columns {
+    pub left: u32,
+    pub master: u32,
+    pub right: u32,
+}
+
+impl CenteredMaster {
+    pub fn columns(&self, window_count: u32) -> Columns {
+        let master = window_count.min(self.master_count.max(1));
+        let stack = window_count - master;
+        let left = stack / 2;
+        Columns {
+            left,
+            master,
+            right: stack - left,
+        }
+    }
+
+    /// the width proportions of the left, master and right columns. empty columns get nothing.
+    pub fn proportions(&self, columns: Columns) -> [f32; 3] {
+        let factor = self.master_factor.clamp(
+            MasterParams::MIN_MASTER_FACTOR,
+            MasterParams::MAX_MASTER_FACTOR,
+        );
+        match (columns.left, columns.right) {
+            (_, 0) => [0.0, 1.0, 0.0],
+            (0, _) => [0.0, factor, 1.0 - factor],
+            _ => [(1.0 - factor) / 2.0, factor, (1.0 - factor) / 2.0],
+        }
+    }
+}
+
+impl LayoutGenerator for CenteredMaster {
+    fn layout(&self, window_count: u32) -> LayoutNode {
+        let root = LayoutNode::new();
+        root.set_gaps(self.outer_gaps);
+        root.set_dir(LayoutDir::Row);
+        let columns = self.columns(window_count);
+        let [left, master, right] = self.proportions(columns);
+        // the traversal index decides which column gets windows first: master, then right, then left.
+        // empty stacks are left out
+        for (windows, proportion, traversal_index) in [
+            (columns.left, left, 2),
+            (columns.master, master, 0),
+            (columns.right, right, 1),
+        ] {
+            if windows == 0 {
+                continue;
+            }
+            let node = LayoutNode::new_with_traversal_index(traversal_index);
+            node.set_dir(LayoutDir::Column);
+            node.set_size_proportion(proportion);
+            for _ in 0..windows {
+                let leaf = LayoutNode::new();
+                leaf.set_gaps(self.inner_gaps);
+                node.add_child(leaf);
+            }
+            root.add_child(node);
+        }
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_hold_every_window() {
+        let generator = CenteredMaster::default();
+        for window_count in 0..=10 {
+            let Columns {
+                left,
+                master,
+                right,
+            } = generator.columns(window_count);
+            assert_eq!(left + master + right, window_count);
+            assert_eq!(master, window_count.min(1));
+            assert!(
+                left <= right && right - left <= 1,
+                "{window_count}: {left} {right}"
+            );
+        }
+    }
+
+    #[test]
+    fn columns_for_small_counts() {
+        let generator = CenteredMaster::default();
+        let columns = |left, master, right| Columns {
+            left,
+            master,
+            right,
+        };
+        assert_eq!(generator.columns(0), columns(0, 0, 0));
+        assert_eq!(generator.columns(1), columns(0, 1, 0));
+        assert_eq!(generator.columns(2), columns(0, 1, 1));
+        assert_eq!(generator.columns(3), columns(1, 1, 1));
+        assert_eq!(generator.columns(4), columns(1, 1, 2));
+        assert_eq!(generator.columns(10), columns(4, 1, 5));
+    }
+
+    #[test]
+    fn master_count_fills_the_master_column_first() {
+        let generator = CenteredMaster {
+            master_count: 2,
+            ..Default::default()
+        };
+        assert_eq!(generator.columns(1).master, 1);
+        assert_eq!(generator.columns(2).master, 2);
+        assert_eq!(
+            generator.columns(5),
+            Columns {
+                left: 1,
+                master: 2,
+                right: 2
+            }
+        );
+    }
+
+    #[test]
+    fn proportions_cover_the_output() {
+        let generator = CenteredMaster {
+            master_factor: 0.6,
+            ..Default::default()
+        };
+        for window_count in 1..=10 {
+            let columns = generator.columns(window_count);
+            let proportions = generator.proportions(columns);
+            assert!((proportions.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+            assert_eq!(proportions[0] == 0.0, columns.left == 0);
+            assert_eq!(proportions[2] == 0.0, columns.right == 0);
+        }
+        assert_eq!(generator.proportions(generator.columns(1)), [0.0, 1.0, 0.0]);
+        assert_eq!(generator.proportions(generator.columns(3))[1], 0.6);
+    }
+
+    /// the tree `layout` should return: a row with the outer gaps holding `columns`, each a column with
+    /// `(windows, proportion, traversal_index)` and a leaf with the inner gaps per window. `LayoutNode` is
+    /// opaque, so trees are compared through their debug output.
+    fn expected_tree(columns: &[(u32, f32, u32)]) -> String {
+        let root = LayoutNode::new();
+        root.set_gaps(Gaps::from(4.0));
+        root.set_dir(LayoutDir::Row);
+        for &(windows, proportion, traversal_index) in columns {
+            let column = LayoutNode::new();
+            column.set_traversal_index(traversal_index);
+            column.set_dir(LayoutDir::Column);
+            column.set_size_proportion(proportion);
+            column.set_children((0..windows).map(|_| {
+                let leaf = LayoutNode::new();
+                leaf.set_gaps(Gaps::from(4.0));
+                leaf
+            }));
+            root.add_child(column);
+        }
+        format!("{root:?}")
+    }
+
+    #[test]
+    fn layout_tree_for_up_to_ten_windows() {
+        let generator = CenteredMaster::default();
+        let layout = |window_count| format!("{:?}", generator.layout(window_count));
+        assert_eq!(layout(0), expected_tree(&[]));
+        assert_eq!(layout(1), expected_tree(&[(1, 1.0, 0)]));
+        assert_eq!(layout(2), expected_tree(&[(1, 0.5, 0), (1, 0.5, 1)]));
+        for (window_count, left, right) in [
+            (3, 1, 1),
+            (4, 1, 2),
+            (5, 2, 2),
+            (6, 2, 3),
+            (7, 3, 3),
+            (8, 3, 4),
+            (9, 4, 4),
+            (10, 4, 5),
+        ] {
+            assert_eq!(
+                layout(window_count),
+                expected_tree(&[(left, 0.25, 2), (1, 0.5, 0), (right, 0.25, 1)]),
+                "{window_count} windows"
+            );
+        }
+        assert_ne!(layout(4), layout(5));
+    }
+
+    #[test]
+    fn master_factor_is_clamped() {
+        let generator = CenteredMaster {
+            master_factor: 1.5,
+            ..Default::default()
+        };
+        let [_, master, right] = generator.proportions(generator.columns(2));
+        assert_eq!(master, MasterParams::MAX_MASTER_FACTOR);
+        assert!(right > 0.0);
+    }
+}
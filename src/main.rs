@@ -1,9 +1,8 @@
-use std::cmp::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::time::Duration;
 
-use list_zipper::{SequenceDirection, Zipper};
+use list_zipper::SequenceDirection;
 use pinnacle_api::input;
 use pinnacle_api::input::Bind;
 use pinnacle_api::input::Keysym;
@@ -13,123 +12,128 @@ use pinnacle_api::input::libinput::DeviceHandle;
 use pinnacle_api::input::libinput::TapButtonMap;
 use pinnacle_api::input::{Mod, MouseButton};
 use pinnacle_api::layout;
-use pinnacle_api::layout::LayoutGenerator;
 use pinnacle_api::layout::LayoutNode;
+use pinnacle_api::layout::LayoutRequester;
 use pinnacle_api::layout::LayoutResponse;
-use pinnacle_api::layout::generators::Cycle;
-use pinnacle_api::layout::generators::MasterStack;
 use pinnacle_api::output;
-use pinnacle_api::output::Mode;
 use pinnacle_api::output::OutputHandle;
 use pinnacle_api::process::Command;
 use pinnacle_api::signal::InputSignal;
 use pinnacle_api::signal::OutputSignal;
+use pinnacle_api::signal::TagSignal;
 use pinnacle_api::signal::WindowSignal;
 use pinnacle_api::tag;
+use pinnacle_api::tag::TagHandle;
 use pinnacle_api::util::Batch;
 use pinnacle_api::util::Direction;
-use pinnacle_api::util::Size;
 use pinnacle_api::window;
-use pinnacle_api::window::VrrDemand;
 use pinnacle_api::window::WindowHandle;
 
-#[cfg(feature = "snowcap")]
-use pinnacle_api::{experimental::snowcap_api::widget::Color, snowcap::FocusBorder};
-use tokio::time::sleep;
-use tokio::time::timeout;
 use tracing_subscriber::EnvFilter;
-use users::get_current_uid;
-
-use crate::uwsm_command::UwsmCommand;
 
+use crate::arrangement::Arranger;
+use crate::autostart::Autostart;
+#[cfg(feature = "snowcap")]
+use crate::borders::Borders;
+use crate::focus_history::FocusHistory;
+use crate::layouts::Layouts;
+use crate::navigation::{CycleScope, cycle_next, move_focus, swap_windows};
+use crate::output_migration::Migrator;
+use crate::scratchpads::Scratchpads;
+use crate::session::Session;
+use crate::sticky::Sticky;
+use crate::supervisor::{Readiness, Service, Supervisor};
+use crate::swallowing::Swallowing;
+use crate::units::UnitAction;
+use crate::urgency::Urgency;
+
+pub mod arrangement;
+pub mod autostart;
+#[cfg(feature = "snowcap")]
+pub mod borders;
+pub mod config_file;
+pub mod focus_history;
+pub mod launch_context;
+pub mod layouts;
+pub mod navigation;
+pub mod output_migration;
+pub mod output_profiles;
+pub mod process_tree;
+pub mod scratchpads;
+pub mod session;
+pub mod state;
+pub mod sticky;
+pub mod supervisor;
+pub mod swallowing;
+pub mod theme;
+pub mod units;
+pub mod urgency;
 pub mod uwsm_command;
+pub mod window_rules;
 
 fn setup_logger() {
     let filter = EnvFilter::from_default_env();
     tracing_subscriber::fmt().with_env_filter(filter).init();
 }
 
-fn cycle_next(
-    focused: Option<WindowHandle>,
-    dir: SequenceDirection,
-    action: impl FnOnce(&WindowHandle, &WindowHandle),
+/// change the layout parameters of the first active tag on the focused output with `f`, then re-layout
+/// the output if `f` changed anything. the changes are saved once the layouts are unlocked.
+fn adjust_layout(
+    cycler: &Mutex<Layouts>,
+    requester: &LayoutRequester,
+    f: impl FnOnce(&mut Layouts, &TagHandle) -> bool,
 ) {
-    if let Some(focused) = focused {
-        let mut windows = tag::get_all()
-            .filter(|t| t.active())
-            .flat_map(|t| t.windows())
-            .collect::<Zipper<_>>();
-
-        if let Some(next) = windows.refocus(|win| win == &focused).step(dir).focus() {
-            action(&focused, next)
-        }
-    }
-}
+    let Some(focused_op) = output::get_focused() else {
+        return;
+    };
+    let Some(first_active_tag) = focused_op
+        .tags()
+        .batch_find(|tag| Box::pin(tag.active_async()), |active| *active)
+    else {
+        return;
+    };
 
-#[cfg(feature = "snowcap")]
-fn make_fb(win: &WindowHandle) {
-    FocusBorder {
-        unfocused_color: Color::rgb(
-            (0x3c as f32) / (0xff as f32),
-            (0x2c as f32) / (0xff as f32),
-            (0x1c as f32) / (0xff as f32),
-        ),
-        focused_color: Color::rgb(
-            (0xee as f32) / (0xff as f32),
-            (0xde as f32) / (0xff as f32),
-            (0xce as f32) / (0xff as f32),
-        ),
-        thickness: 2,
-        ..FocusBorder::new(win)
+    let (changed, unsaved) = {
+        let mut layouts = cycler.lock().unwrap();
+        let changed = f(&mut layouts, &first_active_tag);
+        (changed, layouts.take_unsaved())
+    };
+    if changed {
+        requester.request_layout_on_output(&focused_op);
     }
-    .decorate()
-    .map_or_else(
-        |err| {
-            println!("failed to decorate window: {err}");
-        },
-        |_| (),
-    )
-}
-
-fn move_focus(focused: &WindowHandle, next: &WindowHandle) {
-    if focused.maximized() || next.maximized() {
-        focused.lower();
-        next.set_maximized(true);
-        next.raise();
+    if let Some(saved) = unsaved {
+        saved.write();
     }
-    next.set_focused(true);
-}
-
-fn swap_windows(focused: &WindowHandle, next: &WindowHandle) {
-    focused.swap(next);
-    focused.set_focused(true);
 }
 
-async fn ensure_emacsclient_spawned() {
-    let uid = get_current_uid();
-    let args = ["-c", "-s", &format!("/run/user/{uid}/emacs/server")];
-    while let Some(child) = UwsmCommand::new("emacsclient").args(args).spawn()
-        && let Ok(res) = timeout(Duration::from_secs(1), child.wait_async()).await
-        && res.exit_code != Some(0)
-    {
-        sleep(Duration::from_secs(1)).await;
+/// remember where `window` is among the tiled windows of its output's first active tag, so that monocle
+/// shows it. this is kept up to date whatever the layout, so that switching to monocle shows the focused
+/// window right away.
+fn track_focus(cycler: &Mutex<Layouts>, window: &WindowHandle) {
+    let Some(tag) = window
+        .output()
+        .and_then(|output| output.active_tags().next())
+    else {
+        return;
+    };
+    if let Some(position) = layouts::tiled_position(window) {
+        cycler.lock().unwrap().set_focused(tag, position);
     }
 }
 
-fn size_cmp(size1: &Size, size2: &Size) -> Ordering {
-    (size1.w * size1.h).cmp(&(size2.w * size2.h))
-}
-
-fn mode_cmp(mode1: &Mode, mode2: &Mode) -> Ordering {
-    size_cmp(&mode1.size, &mode2.size).then(mode1.refresh_rate_mhz.cmp(&mode2.refresh_rate_mhz))
-}
-
-fn ensure_bar(output: &OutputHandle) {
+/// (re)open the bar on `output`. opening it fails while the eww daemon isn't up yet, so it's retried.
+fn ensure_bar(supervisor: &Arc<Supervisor>, output: &OutputHandle) {
     let output_name = output.name();
     let eww_service = format!("eww-open@{output_name}");
-    Command::new("systemctl")
-        .args(["restart", "--user", &eww_service])
-        .spawn();
+    let service = Service::plain(
+        &eww_service,
+        ["systemctl", "restart", "--user", &eww_service],
+        Readiness::Exit {
+            grace: Duration::from_secs(10),
+        },
+    );
+    let supervisor = supervisor.clone();
+    tokio::spawn(async move { supervisor.run(service).await });
 }
 
 /// `config` sets up the pinnacle configuration via the `pinnacle_api`
@@ -138,9 +142,18 @@ async fn config() {
 
     // Change the mod key to `Alt` when running as a nested window.
     let mod_key = Mod::ALT;
-    let mod4_key = Mod::SUPER;
 
-    let terminal = "wezterm";
+    let (config, config_errors) = config_file::load(mod_key);
+
+    // where windows were when the session was last saved, to put them back as they're mapped
+    let session = Arc::new(Session::load());
+    tokio::spawn({
+        let session = session.clone();
+        async move { session.save_periodically().await }
+    });
+
+    // keeps daemons and clients that need them running
+    let supervisor = Arc::new(Supervisor::default());
 
     //------------------------
     // Mousebinds            |
@@ -186,6 +199,8 @@ async fn config() {
     // Keybinds              |
     //------------------------
 
+    // config binds on these chords are rejected, see `config_file::BUILTIN_BINDS`
+
     input::keybind(mod_key, 't')
         .on_press(|| {
             if let Some(w) = window::get_focused() {
@@ -205,14 +220,25 @@ async fn config() {
     //     .description("Show the bindings overlay");
 
     // `mod_key + shift + q` quits Pinnacle
+    //
+    // quitting and reloading are left to the compositor, so they work even when the config is stuck. the
+    // session is saved alongside, which may lose the race with the compositor; the periodic save covers that
     #[cfg(not(feature = "snowcap"))]
     input::keybind(mod_key | Mod::SHIFT, 'q')
+        .on_press({
+            let session = session.clone();
+            move || session.save()
+        })
         .set_as_quit()
         .group("Compositor")
         .description("Quit Pinnacle");
 
     // mod + q reloads the config
     input::keybind(mod_key, 'q')
+        .on_press({
+            let session = session.clone();
+            move || session.save()
+        })
         .set_as_reload_config()
         .group("Compositor")
         .description("Reload Pinnacle Config");
@@ -265,16 +291,25 @@ async fn config() {
 
     #[cfg(feature = "snowcap")]
     {
-        // `mod_key + shift + q` shows the quit prompt
+        // `mod_key + shift + q` shows the quit prompt. the prompt quits on its own, so the session is
+        // saved before asking
         input::keybind(mod_key | Mod::SHIFT, 'q')
-            .on_press(|| {
-                pinnacle_api::snowcap::QuitPrompt::new().show();
+            .on_press({
+                let session = session.clone();
+                move || {
+                    session.save();
+                    pinnacle_api::snowcap::QuitPrompt::new().show();
+                }
             })
             .group("Compositor")
             .description("Show quit prompt");
 
         // `mod_key + ctrl + shift + q` for the hard shutdown
         input::keybind(mod_key | Mod::CTRL | Mod::SHIFT, 'q')
+            .on_press({
+                let session = session.clone();
+                move || session.save()
+            })
             .set_as_quit()
             .group("Compositor")
             .description("Quit Pinnacle without prompt");
@@ -290,70 +325,73 @@ async fn config() {
         .group("Window")
         .description("Close the focused window");
 
-    input::keybind(mod_key | Mod::SHIFT, 'p')
-        .on_press(move || {
-            Command::new("clipcat-menu").spawn();
-        })
-        .group("Process")
-        .description("Open Clipboard History");
-
-    input::keybind(mod_key, 'o')
-        .on_press(move || {
-            Command::new("rofi-rbw").spawn();
-        })
-        .group("Process")
-        .description("Bitwarden Passwords");
+    // apps spawned through `uwsm app` get their own unit, so a hung app can be stopped as a whole:
+    // `mod_key + Delete` stops the focused window's unit, `mod_key + ctrl + Delete` restarts it and
+    // `mod_key + shift + Delete` kills it
+    for (mods, action, description) in [
+        (
+            Mod::empty(),
+            UnitAction::Stop,
+            "Stop the focused window's unit",
+        ),
+        (
+            Mod::CTRL,
+            UnitAction::Restart,
+            "Restart the focused window's unit",
+        ),
+        (
+            Mod::SHIFT,
+            UnitAction::Kill,
+            "Kill the focused window's unit",
+        ),
+    ] {
+        input::keybind(mod_key | mods, Keysym::Delete)
+            .on_press(move || units::act_on_focused(action))
+            .group("Window")
+            .description(description);
+    }
 
-    input::keybind(mod_key, 'p')
-        .on_press(|| {
-            Command::new("rofi")
-                .args([
-                    "-show",
-                    "combi",
-                    "-modes",
-                    "combi",
-                    "-combi-modes",
-                    "drun,run,calc,window,ssh",
-                ])
-                .spawn();
+    // `mod_key + ctrl + u` shows the status and journal of the focused window's unit
+    input::keybind(mod_key | Mod::CTRL, 'u')
+        .on_press({
+            let terminal = config.terminal.clone();
+            move || units::show_focused(&terminal)
         })
-        .group("Process")
-        .description("spawn the application launcher");
+        .group("Window")
+        .description("Show the focused window's unit");
 
-    input::keybind(mod_key, 'n')
-        .on_press(|| {
-            Command::new("rofi")
-                .args(["-show", "emoji", "-modes", "emoji"])
-                .spawn();
+    // `mod_key + ctrl + i` shows how the supervised services, like emacsclient and the bars, are doing
+    input::keybind(mod_key | Mod::CTRL, 'i')
+        .on_press({
+            let supervisor = supervisor.clone();
+            move || supervisor.show_statuses()
         })
-        .group("Process")
-        .description("spawn the application launcher");
+        .group("System")
+        .description("Show the status of supervised services");
 
-    input::keybind(mod_key, 'i')
-        .on_press(|| {
-            Command::new("rofi")
-                .args([
-                    "-show",
-                    "file-browser-extended",
-                    "-modes",
-                    "file-browser-extended",
-                ])
-                .spawn();
-        })
-        .group("Process")
-        .description("spawn the application launcher");
+    // process launchers come from the config file
+    for bind in &config.binds {
+        bind.register();
+    }
 
-    input::keybind(mod4_key, 'p')
-        .on_press(|| {
-            Command::new("rofi-screenshot").spawn();
-        })
-        .group("Process")
-        .description("take a screenshot");
+    // so do scratchpads, windows toggled over the active tag
+    let scratchpads = Arc::new(Scratchpads::new(config.scratchpads.clone()));
+    for pad in scratchpads.pads() {
+        config_file::keybind(pad.mods, &pad.key)
+            .on_press({
+                let scratchpads = scratchpads.clone();
+                let name = pad.name.clone();
+                move || scratchpads.toggle(&name)
+            })
+            .group("Scratchpad")
+            .description(format!("Toggle the {} scratchpad", pad.name));
+    }
 
     input::keybind(mod_key, 'j')
         .on_press(|| {
             cycle_next(
                 window::get_focused(),
+                CycleScope::FocusedOutput,
                 SequenceDirection::Original,
                 move_focus,
             );
@@ -361,38 +399,109 @@ async fn config() {
         .group("Window")
         .description("focus next window");
 
-    input::keybind(mod_key, Keysym::Tab)
+    input::keybind(mod_key, 'k')
         .on_press(|| {
             cycle_next(
                 window::get_focused(),
-                SequenceDirection::Original,
+                CycleScope::FocusedOutput,
+                SequenceDirection::Reverse,
                 move_focus,
             );
         })
         .group("Window")
         .description("focus prev window");
 
-    input::keybind(mod_key, 'k')
+    // `mod_key + tab` walks back through recently focused windows, switching tags and outputs as needed.
+    // a single press goes back to the previous window; presses in quick succession keep walking back
+    let focus_history = Arc::new(FocusHistory::default());
+    input::keybind(mod_key, Keysym::Tab)
+        .on_press({
+            let focus_history = focus_history.clone();
+            move || focus_history.cycle(1)
+        })
+        .group("Window")
+        .description("focus recently used windows");
+
+    input::keybind(mod_key | Mod::SHIFT, Keysym::Tab)
+        .on_press({
+            let focus_history = focus_history.clone();
+            move || focus_history.cycle(-1)
+        })
+        .group("Window")
+        .description("focus recently used windows in reverse");
+
+    input::keybind(mod_key, Keysym::BackSpace)
+        .on_press({
+            let focus_history = focus_history.clone();
+            move || focus_history.previous()
+        })
+        .group("Window")
+        .description("focus the previously focused window");
+
+    // `mod_key + u` goes to the window that has been asking for attention the longest
+    let urgency = Arc::new(Urgency::new(config.attention.clone()));
+    input::keybind(mod_key, 'u')
+        .on_press({
+            let urgency = urgency.clone();
+            move || urgency.jump_to_oldest()
+        })
+        .group("Window")
+        .description("focus the oldest urgent window");
+
+    let sticky = Arc::new(Sticky::default());
+
+    input::keybind(mod_key | Mod::CTRL, 'j')
         .on_press(|| {
             cycle_next(
                 window::get_focused(),
-                SequenceDirection::Reverse,
+                CycleScope::AllOutputs,
+                SequenceDirection::Original,
                 move_focus,
             );
         })
         .group("Window")
-        .description("focus prev window");
+        .description("focus next window on any output");
 
-    input::keybind(mod_key | Mod::SHIFT, Keysym::Tab)
+    input::keybind(mod_key | Mod::CTRL, 'k')
         .on_press(|| {
             cycle_next(
                 window::get_focused(),
+                CycleScope::AllOutputs,
                 SequenceDirection::Reverse,
                 move_focus,
             );
         })
         .group("Window")
-        .description("focus next window");
+        .description("focus prev window on any output");
+
+    // `mod_key + arrows` focuses the window in that direction, continuing onto the neighbouring output
+    // `mod_key + shift + arrows` swaps with it, or moves the window to the neighbouring output
+    for (key, dir, name) in [
+        (Keysym::Left, Direction::Left, "left"),
+        (Keysym::Right, Direction::Right, "right"),
+        (Keysym::Up, Direction::Up, "up"),
+        (Keysym::Down, Direction::Down, "down"),
+    ] {
+        input::keybind(mod_key, key)
+            .on_press(move || navigation::focus_in_direction(dir))
+            .group("Window")
+            .description(format!("focus the window {name}"));
+
+        input::keybind(mod_key | Mod::SHIFT, key)
+            .on_press(move || navigation::swap_in_direction(dir))
+            .group("Window")
+            .description(format!("swap with the window {name}"));
+    }
+
+    input::keybind(mod_key | Mod::CTRL, Keysym::Right)
+        .on_press(|| navigation::move_to_adjacent_output(1))
+        .group("Window")
+        .description("move window to the next output");
+
+    input::keybind(mod_key | Mod::CTRL, Keysym::Left)
+        .on_press(|| navigation::move_to_adjacent_output(-1))
+        .group("Window")
+        .description("move window to the previous output");
 
     //------------------------
     // Layouts               |
@@ -410,22 +519,41 @@ async fn config() {
     // a tree of layout nodes that determines how windows are laid out.
     //
     // There are currently six built-in layout generators, one of which delegates to other
-    // generators as shown below.
-
-    fn into_box<'a, T: LayoutGenerator + Send + 'a>(
-        generator: T,
-    ) -> Box<dyn LayoutGenerator + Send + 'a> {
-        Box::new(generator) as _
-    }
+    // generators. `layouts::Layouts` wraps it to cycle through the layouts configured in `config.toml`,
+    // to start each tag on its configured default layout and to keep each tag's layout across reloads.
 
     // Create a cycling layout generator that can cycle between layouts on different tags.
-    let cycler = Arc::new(Mutex::new(Cycle::new([into_box(MasterStack::default())])));
+    let cycler = Arc::new(Mutex::new(Layouts::new(config.layouts.clone())));
+
+    #[cfg(feature = "snowcap")]
+    let borders = Arc::new(Borders::new(
+        config.theme.clone(),
+        urgency.clone(),
+        sticky.clone(),
+    ));
+    // restyle the borders as soon as the theme in the config file changes
+    #[cfg(feature = "snowcap")]
+    tokio::spawn(config_file::watch(mod_key, {
+        let borders = borders.clone();
+        move |config| borders.set_theme(config.theme)
+    }));
 
     // Use the cycling layout generator to manage layout requests.
     // This returns a layout requester that allows you to request layouts manually.
     let layout_requester = layout::manage({
         let cycler = cycler.clone();
+        #[cfg(feature = "snowcap")]
+        let borders = borders.clone();
         move |args| {
+            // windows came, went or moved, so smart borders may need to show or hide. this talks to the
+            // compositor, so it can't hold up the layout
+            #[cfg(feature = "snowcap")]
+            tokio::spawn({
+                let borders = borders.clone();
+                let output = args.output.clone();
+                async move { borders.refresh(&output) }
+            });
+
             let Some(tag) = args.tags.first() else {
                 return LayoutResponse {
                     root_node: LayoutNode::new(),
@@ -475,27 +603,33 @@ async fn config() {
         .group("Window")
         .description("Toggle maximized on the focused window");
 
+    // `mod_key + ctrl + s` makes the focused window follow the active tag of its output
+    input::keybind(mod_key | Mod::CTRL, 's')
+        .on_press({
+            let sticky = sticky.clone();
+            #[cfg(feature = "snowcap")]
+            let borders = borders.clone();
+            move || {
+                if let Some(window) = window::get_focused() {
+                    sticky.toggle(&window);
+                    #[cfg(feature = "snowcap")]
+                    borders.decorate(&window);
+                }
+            }
+        })
+        .group("Window")
+        .description("Toggle sticky on the focused window");
+
     // `mod_key + space` cycles to the next layout
     input::keybind(mod_key, Keysym::space)
         .on_press({
             let cycler = cycler.clone();
             let requester = layout_requester.clone();
             move || {
-                let Some(focused_op) = output::get_focused() else {
-                    return;
-                };
-                let Some(first_active_tag) = focused_op
-                    .tags()
-                    .batch_find(|tag| Box::pin(tag.active_async()), |active| *active)
-                else {
-                    return;
-                };
-
-                cycler
-                    .lock()
-                    .unwrap()
-                    .cycle_layout_forward(&first_active_tag);
-                requester.request_layout_on_output(&focused_op);
+                adjust_layout(&cycler, &requester, |layouts, tag| {
+                    layouts.cycle_layout_forward(tag);
+                    true
+                })
             }
         })
         .group("Layout")
@@ -507,21 +641,10 @@ async fn config() {
             let cycler = cycler.clone();
             let requester = layout_requester.clone();
             move || {
-                let Some(focused_op) = output::get_focused() else {
-                    return;
-                };
-                let Some(first_active_tag) = focused_op
-                    .tags()
-                    .batch_find(|tag| Box::pin(tag.active_async()), |active| *active)
-                else {
-                    return;
-                };
-
-                cycler
-                    .lock()
-                    .unwrap()
-                    .cycle_layout_backward(&first_active_tag);
-                requester.request_layout_on_output(&focused_op);
+                adjust_layout(&cycler, &requester, |layouts, tag| {
+                    layouts.cycle_layout_backward(tag);
+                    true
+                })
             }
         })
         .group("Layout")
@@ -531,6 +654,7 @@ async fn config() {
         .on_press(|| {
             cycle_next(
                 window::get_focused(),
+                CycleScope::FocusedOutput,
                 SequenceDirection::Original,
                 swap_windows,
             );
@@ -542,6 +666,7 @@ async fn config() {
         .on_press(|| {
             cycle_next(
                 window::get_focused(),
+                CycleScope::FocusedOutput,
                 SequenceDirection::Reverse,
                 swap_windows,
             );
@@ -550,113 +675,136 @@ async fn config() {
         .description("shift window backwards");
 
     input::keybind(mod_key, 'h')
-        .on_press(move || {
-            if let Some(focused) = window::get_focused() {
-                let master = focused
-                    .in_direction(Direction::Left)
-                    .next()
-                    .unwrap_or(focused);
-                let resize = master
-                    .output()
-                    .and_then(|output| output.current_mode())
-                    .map(|mode| (mode.size.w as i32) / 20)
-                    .unwrap_or(192);
-                master.resize_tile(0, -resize, 0, 0);
+        .on_press({
+            let cycler = cycler.clone();
+            let requester = layout_requester.clone();
+            move || {
+                adjust_layout(&cycler, &requester, |layouts, tag| {
+                    layouts.adjust_master_factor(tag, -0.05)
+                })
             }
         })
-        .group("Window")
+        .group("Layout")
         .description("decrease master pane size");
 
     input::keybind(mod_key, 'l')
-        .on_press(move || {
-            if let Some(focused) = window::get_focused() {
-                let master = focused
-                    .in_direction(Direction::Left)
-                    .next()
-                    .unwrap_or(focused);
-                let resize = master
-                    .output()
-                    .and_then(|output| output.current_mode())
-                    .map(|mode| (mode.size.w as i32) / 20)
-                    .unwrap_or(192);
-                master.resize_tile(0, resize, 0, 0);
+        .on_press({
+            let cycler = cycler.clone();
+            let requester = layout_requester.clone();
+            move || {
+                adjust_layout(&cycler, &requester, |layouts, tag| {
+                    layouts.adjust_master_factor(tag, 0.05)
+                })
             }
         })
-        .group("Window")
+        .group("Layout")
         .description("increase master pane size");
 
-    let terminal_frame_name = "(name . \"emacsclient\")";
-    let mu4e_frame_name = "(name . \"mu4e\")";
-    let fullscreen = "(fullscreen . fullheight)";
-    let auto_raise = "(auto-raise . nil)";
-    let auto_lower = "(auto-lower . nil)";
-    let wait_for_wm = "(wait-for-wm . t)";
+    input::keybind(mod_key, Keysym::comma)
+        .on_press({
+            let cycler = cycler.clone();
+            let requester = layout_requester.clone();
+            move || {
+                adjust_layout(&cycler, &requester, |layouts, tag| {
+                    layouts.adjust_master_count(tag, 1)
+                })
+            }
+        })
+        .group("Layout")
+        .description("add a window to the master pane");
 
-    // `M-S-RET` spawns an eat terminal
-    input::keybind(mod_key | Mod::SHIFT, Keysym::Return)
-        .on_press(move || {
-            UwsmCommand::new("emacsclient")
-                .args([
-                    "-c",
-                    "-F",
-                    &*format!(
-                        "({terminal_frame_name} {fullscreen} {auto_raise} {auto_lower} {wait_for_wm})"
-                    ),
-                    "-e",
-                    "(+eat/here)",
-                ])
-                .spawn();
+    input::keybind(mod_key, Keysym::period)
+        .on_press({
+            let cycler = cycler.clone();
+            let requester = layout_requester.clone();
+            move || {
+                adjust_layout(&cycler, &requester, |layouts, tag| {
+                    layouts.adjust_master_count(tag, -1)
+                })
+            }
         })
-        .group("Process")
-        .description("Open an emacs terminal");
+        .group("Layout")
+        .description("remove a window from the master pane");
 
-    // `M-RET` spawns mu4e
-    input::keybind(mod_key, Keysym::Return)
-        .on_press(move || {
-            UwsmCommand::new("emacsclient")
-                .args(["-c", "-F", &*format!("({mu4e_frame_name})"), "-e", "(mu4e)"])
-                .spawn();
+    input::keybind(mod_key, Keysym::equal)
+        .on_press({
+            let cycler = cycler.clone();
+            let requester = layout_requester.clone();
+            move || adjust_layout(&cycler, &requester, Layouts::reset_master)
         })
-        .group("Process")
-        .description("Open mu4e");
+        .group("Layout")
+        .description("reset the master pane");
+
+    // `mod_key + ctrl + minus/equal` shrinks/grows the gaps on the focused tag
+    for (key, delta, description) in [
+        (Keysym::minus, -2.0, "shrink the gaps"),
+        (Keysym::equal, 2.0, "grow the gaps"),
+    ] {
+        input::keybind(mod_key | Mod::CTRL, key)
+            .on_press({
+                let cycler = cycler.clone();
+                let requester = layout_requester.clone();
+                move || {
+                    adjust_layout(&cycler, &requester, |layouts, tag| {
+                        layouts.adjust_gaps(tag, delta)
+                    })
+                }
+            })
+            .group("Layout")
+            .description(description);
+    }
 
     //------------------------
     // Tags                  |
     //------------------------
 
-    let tag_names = ["I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX", "X"];
-
-    // Setup all monitors with tags "1" through "9"
-    let output_setup = move |output: &OutputHandle| {
-        let output_name = output.name();
-
-        if let Some(mode) = output
-            .modes()
-            // some outputs report a weird 4096x2160 mode that doesn't work well
-            .filter(|mode| mode.size.w <= 3840 && mode.size.h <= 2160)
-            .max_by(mode_cmp)
-        {
-            let Mode {
-                size: Size { w, h },
-                refresh_rate_mhz,
-            } = mode;
-            println!("setting mode {w}x{h}@{refresh_rate_mhz} on {output_name}");
-            output.set_mode(w, h, refresh_rate_mhz);
+    // Setup all monitors with the configured tags, then (re)apply the output profiles now that the
+    // set of connected outputs changed
+    let arranger = Arc::new(Arranger::default());
+    let migrator = Arc::new(Migrator::default());
+    let output_setup = {
+        let tag_names = config.tags.clone();
+        let outputs = config.outputs.clone();
+        let arranger = arranger.clone();
+        let migrator = migrator.clone();
+        let cycler = cycler.clone();
+        move |output: &OutputHandle| {
+            // the hidden scratchpad tag goes last and is left out of everything else
+            let mut tags = tag::add(
+                output,
+                tag_names
+                    .iter()
+                    .map(String::as_str)
+                    .chain([scratchpads::HIDDEN_TAG]),
+            )
+            .collect::<Vec<_>>();
+            tags.pop();
+            tags.first().unwrap().set_active(true);
+            // names come from the compositor, so they're looked up before the layouts are locked
+            let named = tags
+                .iter()
+                .map(|tag| (tag.clone(), tag.name()))
+                .collect::<Vec<_>>();
+            let output_name = output.name();
+            cycler.lock().unwrap().restore(&output_name, &named);
+            migrator.connected(output);
+
+            if !output_profiles::configure_outputs(&outputs) {
+                arranger.arrange_outputs();
+            }
         }
-        output.set_scale(2.0);
-        output.set_vrr(output::Vrr::OnDemand);
-
-        let mut tags = tag::add(output, tag_names);
-        tags.next().unwrap().set_active(true);
     };
-    output::for_each_output(output_setup);
+    output::for_each_output(output_setup.clone());
 
-    for (tag_name, index) in tag_names.into_iter().zip(('1'..='9').chain('0'..='0')) {
+    for (tag_name, index) in config.tags.iter().zip(('1'..='9').chain('0'..='0')) {
         // `mod_key + 1-9` switches to tag "1" to "9"
         input::keybind(mod_key, index)
-            .on_press(move || {
-                if let Some(tag) = tag::get(tag_name) {
-                    tag.switch_to();
+            .on_press({
+                let tag_name = tag_name.clone();
+                move || {
+                    if let Some(tag) = tag::get(&tag_name) {
+                        tag.switch_to();
+                    }
                 }
             })
             .group("Tag")
@@ -664,9 +812,12 @@ async fn config() {
 
         // `mod_key + ctrl + 1-9` toggles tag "1" to "9"
         input::keybind(mod_key | Mod::CTRL, index)
-            .on_press(move || {
-                if let Some(tag) = tag::get(tag_name) {
-                    tag.toggle_active();
+            .on_press({
+                let tag_name = tag_name.clone();
+                move || {
+                    if let Some(tag) = tag::get(&tag_name) {
+                        tag.toggle_active();
+                    }
                 }
             })
             .group("Tag")
@@ -674,12 +825,15 @@ async fn config() {
 
         // `mod_key + shift + 1-9` moves the focused window to tag "1" to "9"
         input::keybind(mod_key | Mod::SHIFT, index)
-            .on_press(move || {
-                if let Some(tag) = tag::get(tag_name)
-                    && let Some(win) = window::get_focused()
-                {
-                    win.move_to_tag(&tag);
-                    tag.switch_to();
+            .on_press({
+                let tag_name = tag_name.clone();
+                move || {
+                    if let Some(tag) = tag::get(&tag_name)
+                        && let Some(win) = window::get_focused()
+                    {
+                        win.move_to_tag(&tag);
+                        tag.switch_to();
+                    }
                 }
             })
             .group("Tag")
@@ -687,17 +841,30 @@ async fn config() {
 
         // `mod_key + ctrl + shift + 1-9` toggles tag "1" to "9" on the focused window
         input::keybind(mod_key | Mod::CTRL | Mod::SHIFT, index)
-            .on_press(move || {
-                if let Some(tg) = tag::get(tag_name)
-                    && let Some(win) = window::get_focused()
-                {
-                    win.toggle_tag(&tg);
+            .on_press({
+                let tag_name = tag_name.clone();
+                move || {
+                    if let Some(tg) = tag::get(&tag_name)
+                        && let Some(win) = window::get_focused()
+                    {
+                        win.toggle_tag(&tg);
+                    }
                 }
             })
             .group("Tag")
             .description(format!("Toggle tag {tag_name} on the focused window"));
     }
 
+    // sticky windows follow every tag switch, whether it comes from a bind or from `switch_to`
+    tag::connect_signal(TagSignal::Active(Box::new({
+        let sticky = sticky.clone();
+        move |tag, active| {
+            if active {
+                sticky.tag_activated(tag);
+            }
+        }
+    })));
+
     fn prep_devices(device: &DeviceHandle) {
         // Enable natural scroll for touchpads
         if device.device_type().is_touchpad() {
@@ -715,46 +882,57 @@ async fn config() {
     input::libinput::for_each_device(prep_devices);
     input::connect_signal(InputSignal::DeviceAdded(Box::new(prep_devices)));
 
-    fn apply_window_rules(window: WindowHandle) {
-        match &*window.app_id() {
-            "firefox" => {
-                window.set_maximized(true);
-                window.set_tags(tag::get("II"));
-            }
-            "org.wezfurlong.wezterm" => {
-                window.set_tags(tag::get("VI"));
-            }
-            "emacs" => {
-                if window.title().contains("emacsclient") {
-                    window.set_maximized(false);
-                    window.set_fullscreen(false);
-                    window.set_tags(tag::get("IV"));
-                } else if window.title().contains("mu4e") {
-                    window.set_maximized(true);
-                    window.set_tags(tag::get("V"));
-                } else {
-                    window.set_maximized(true);
-                    window.set_tags(tag::get("I"));
-                }
-            }
-            "Slack" => {
-                window.set_tags(tag::get("III"));
-                window.set_maximized(true);
-            }
-            _ => {}
+    // redraw the border and update the bar after a window's urgency changed
+    let urgency_changed = {
+        #[cfg(feature = "snowcap")]
+        let borders = borders.clone();
+        let urgency = urgency.clone();
+        move |_window: &WindowHandle| {
+            #[cfg(feature = "snowcap")]
+            borders.decorate(_window);
+            urgency.publish();
         }
+    };
 
-        window.set_decoration_mode(window::DecorationMode::ServerSide);
+    // the apps started with the session, placed where the config says as they're mapped
+    let autostart = Arc::new(Autostart::new(config.autostart.clone()));
 
+    // terminals make way for the windows started from them
+    let swallowing = Arc::new(Swallowing::new(config.swallow.clone()));
+
+    let apply_window_rules = {
+        let rules = Arc::new(config.rules.clone());
+        let scratchpads = scratchpads.clone();
+        let swallowing = swallowing.clone();
+        let session = session.clone();
+        let autostart = autostart.clone();
         #[cfg(feature = "snowcap")]
-        make_fb(&window);
+        let borders = borders.clone();
+        move |window: WindowHandle| {
+            if scratchpads.claim(&window) {
+                window_rules::apply_actions(&window, &Default::default());
+            } else if swallowing.claim(&window) {
+                // swallowed terminals stay parked until the window that swallowed them closes
+            } else {
+                // a window the rules don't place goes where it was launched from, not wherever the user
+                // went while it was starting
+                if !rules.apply(&window) {
+                    launch_context::place(&window);
+                }
+                autostart.place(&window);
+                session.restore(&window);
+                swallowing.window_mapped(&window);
+            }
 
-        window.set_vrr_demand(VrrDemand::when_fullscreen());
-    }
+            #[cfg(feature = "snowcap")]
+            borders.decorate(&window);
+        }
+    };
 
     // Add borders to new windows.
     window::add_window_rule({
         let requester = layout_requester.clone();
+        let apply_window_rules = apply_window_rules.clone();
         move |win| {
             apply_window_rules(win);
             requester.request_layout();
@@ -767,11 +945,29 @@ async fn config() {
     })));
 
     output::connect_signal(OutputSignal::Connect(Box::new(output_setup)));
-    output::connect_signal(OutputSignal::Disconnect(Box::new(|output| {
-        tracing::info!(output = %output.name(), "disconnected output");
+    output::connect_signal(OutputSignal::Disconnect(Box::new({
+        let outputs = config.outputs.clone();
+        let arranger = arranger.clone();
+        move |output| {
+            tracing::info!(output = %output.name(), "disconnected output");
+            migrator.disconnected(output);
+            if !output_profiles::configure_outputs(&outputs) {
+                arranger.arrange_outputs();
+            }
+        }
+    })));
+    // remember outputs moved by hand as the preferred arrangement
+    output::connect_signal(OutputSignal::Move(Box::new({
+        let arranger = arranger.clone();
+        move |_output, _x, _y| {
+            arranger.remember();
+        }
     })));
-    output::connect_signal(OutputSignal::Resize(Box::new(|output, _, _| {
-        ensure_bar(output);
+    output::connect_signal(OutputSignal::Resize(Box::new({
+        let supervisor = supervisor.clone();
+        move |output, _, _| {
+            ensure_bar(&supervisor, output);
+        }
     })));
 
     window::connect_signal(WindowSignal::Created(Box::new({
@@ -783,21 +979,52 @@ async fn config() {
 
     window::connect_signal(WindowSignal::Focused(Box::new({
         let requester = layout_requester.clone();
-        move |_win| {
+        let cycler = cycler.clone();
+        let focus_history = focus_history.clone();
+        let urgency = urgency.clone();
+        let urgency_changed = urgency_changed.clone();
+        move |win| {
+            focus_history.focused(win);
+            track_focus(&cycler, win);
+            if urgency.clear(win) {
+                urgency_changed(win);
+            }
             requester.request_layout();
         }
     })));
 
+    window::connect_signal(WindowSignal::TitleChanged(Box::new({
+        let urgency = urgency.clone();
+        move |win, _title| {
+            if urgency.title_changed(win) {
+                urgency_changed(win);
+            }
+        }
+    })));
+
     window::connect_signal(WindowSignal::LayoutModeChanged(Box::new({
         let requester = layout_requester.clone();
+        #[cfg(feature = "snowcap")]
+        let borders = borders.clone();
         move |_win, _layout_mode| {
+            #[cfg(feature = "snowcap")]
+            borders.decorate(_win);
             requester.request_layout();
         }
     })));
 
     window::connect_signal(WindowSignal::Destroyed(Box::new({
         let requester = layout_requester.clone();
-        move |_win, _title, _appid| {
+        let urgency = urgency.clone();
+        move |win, _title, _appid| {
+            focus_history.forget(win);
+            sticky.forget(win);
+            swallowing.destroyed(win);
+            #[cfg(feature = "snowcap")]
+            borders.forget(win);
+            if urgency.clear(win) {
+                urgency.publish();
+            }
             requester.request_layout();
         }
     })));
@@ -807,17 +1034,17 @@ async fn config() {
         // Show previous crash messages
         pinnacle_api::snowcap::ConfigCrashedMessage::new(error).show();
     }
+    config_file::report_errors(&config_errors);
 
     pinnacle_api::pinnacle::set_xwayland_self_scaling(true);
 
-    // need to delay creating the bar to give the daemon a bit of time to start
-    sleep(Duration::from_secs(1)).await;
-    output::for_each_output(ensure_bar);
-
-    UwsmCommand::new(terminal).unique().once().spawn();
-    UwsmCommand::new("firefox").unique().once().spawn();
+    // the bar is retried until the eww daemon is up
+    output::for_each_output({
+        let supervisor = supervisor.clone();
+        move |output| ensure_bar(&supervisor, output)
+    });
 
-    tokio::spawn(ensure_emacsclient_spawned());
+    tokio::spawn(autostart.run(supervisor));
 
     // Add borders to already existing windows.
     window::get_all().for_each(apply_window_rules);
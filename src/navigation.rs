@@ -0,0 +1,259 @@
+use itertools::Itertools;
+use list_zipper::{SequenceDirection, Zipper};
+use pinnacle_api::output;
+use pinnacle_api::output::OutputHandle;
+use pinnacle_api::util::Direction;
+use pinnacle_api::util::Point;
+use pinnacle_api::util::Size;
+use pinnacle_api::window;
+use pinnacle_api::window::WindowHandle;
+
+use crate::output_migration::retag;
+
+/// a rectangle in the global compositor space.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Rect {
+    pub loc: Point,
+    pub size: Size,
+}
+
+impl Rect {
+    fn right(&self) -> i32 {
+        self.loc.x + self.size.w as i32
+    }
+
+    fn bottom(&self) -> i32 {
+        self.loc.y + self.size.h as i32
+    }
+
+    /// twice the center, so it stays integral.
+    fn center2(&self) -> Point {
+        Point {
+            x: self.loc.x + self.right(),
+            y: self.loc.y + self.bottom(),
+        }
+    }
+
+    fn of_output(output: &OutputHandle) -> Option<Rect> {
+        Some(Rect {
+            loc: output.loc()?,
+            size: output.logical_size()?,
+        })
+    }
+
+    fn of_window(window: &WindowHandle) -> Option<Rect> {
+        Some(Rect {
+            loc: window.loc()?,
+            size: window.size()?,
+        })
+    }
+}
+
+/// the index of the rectangle in `candidates` closest to `from` in `dir`, preferring rectangles that line
+/// up with `from` on the other axis.
+pub fn neighbour(from: Rect, candidates: &[Rect], dir: Direction) -> Option<usize> {
+    let center = from.center2();
+    candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, rect)| {
+            let gap = match dir {
+                Direction::Left => from.loc.x - rect.right(),
+                Direction::Right => rect.loc.x - from.right(),
+                Direction::Up => from.loc.y - rect.bottom(),
+                Direction::Down => rect.loc.y - from.bottom(),
+            };
+            let offset = match dir {
+                Direction::Left | Direction::Right => (rect.center2().y - center.y).abs(),
+                Direction::Up | Direction::Down => (rect.center2().x - center.x).abs(),
+            };
+            (gap >= 0).then_some((idx, (gap, offset)))
+        })
+        .min_by_key(|(_, key)| *key)
+        .map(|(idx, _)| idx)
+}
+
+/// the output next to `from` in `dir`.
+fn output_in_direction(from: &OutputHandle, dir: Direction) -> Option<OutputHandle> {
+    let from_rect = Rect::of_output(from)?;
+    let (outputs, rects): (Vec<_>, Vec<_>) = output::get_all_enabled()
+        .filter(|op| op != from)
+        .filter_map(|op| Rect::of_output(&op).map(|rect| (op, rect)))
+        .unzip();
+    neighbour(from_rect, &rects, dir).map(|idx| outputs[idx].clone())
+}
+
+/// the visible window on `output` closest to the edge that is entered when moving in `dir`, i.e. the
+/// leftmost window when moving right.
+fn window_at_edge(output: &OutputHandle, dir: Direction, from: Point) -> Option<WindowHandle> {
+    output
+        .active_tags()
+        .flat_map(|tag| tag.windows())
+        .filter_map(|window| Some((Rect::of_window(&window)?, window)))
+        .min_by_key(|(rect, _)| {
+            let center = rect.center2();
+            match dir {
+                Direction::Left => (-rect.right(), (center.y - from.y).abs()),
+                Direction::Right => (rect.loc.x, (center.y - from.y).abs()),
+                Direction::Up => (-rect.bottom(), (center.x - from.x).abs()),
+                Direction::Down => (rect.loc.y, (center.x - from.x).abs()),
+            }
+        })
+        .map(|(_, window)| window)
+}
+
+/// which windows [`cycle_next`] goes through.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CycleScope {
+    /// the windows on the active tags of the focused window's output.
+    FocusedOutput,
+    /// the windows on the active tags of every output, output by output.
+    AllOutputs,
+}
+
+/// the visible windows in `scope`, each once, ordered by output position and then by where the layout put
+/// them: left to right, top to bottom.
+fn cycle_windows(scope: CycleScope, focused: &WindowHandle) -> Vec<WindowHandle> {
+    let outputs = match scope {
+        CycleScope::FocusedOutput => focused
+            .output()
+            .or_else(output::get_focused)
+            .into_iter()
+            .collect(),
+        CycleScope::AllOutputs => outputs_by_position(),
+    };
+    let mut windows = outputs
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, op)| {
+            op.active_tags()
+                .flat_map(|tag| tag.windows())
+                .map(move |window| (idx, window))
+        })
+        .unique_by(|(_, window)| window.clone())
+        .map(|(idx, window)| (idx, window.loc().map(|Point { x, y }| (x, y)), window))
+        .collect::<Vec<_>>();
+    // windows that aren't placed yet go last on their output
+    windows.sort_by_key(|(idx, loc, _)| (*idx, loc.is_none(), *loc));
+    windows.into_iter().map(|(_, _, window)| window).collect()
+}
+
+/// run `action` on the focused window and the window after it in `dir` among the windows in `scope`.
+pub fn cycle_next(
+    focused: Option<WindowHandle>,
+    scope: CycleScope,
+    dir: SequenceDirection,
+    action: impl FnOnce(&WindowHandle, &WindowHandle),
+) {
+    if let Some(focused) = focused {
+        let mut windows = cycle_windows(scope, &focused)
+            .into_iter()
+            .collect::<Zipper<_>>();
+
+        if let Some(next) = windows.refocus(|win| win == &focused).step(dir).focus() {
+            action(&focused, next)
+        }
+    }
+}
+
+/// focus `next` instead of `focused`, handing over maximized state so a maximized window stays in front.
+pub fn move_focus(focused: &WindowHandle, next: &WindowHandle) {
+    if focused.maximized() || next.maximized() {
+        focused.lower();
+        next.set_maximized(true);
+        next.raise();
+    }
+    next.set_focused(true);
+}
+
+pub fn swap_windows(focused: &WindowHandle, next: &WindowHandle) {
+    focused.swap(next);
+    focused.set_focused(true);
+}
+
+/// focus the window in `dir` from the focused one, or the nearest window on the neighbouring output if
+/// there is none on this output.
+pub fn focus_in_direction(dir: Direction) {
+    let focused = window::get_focused();
+    if let Some(focused) = &focused
+        && let Some(next) = focused.in_direction(dir).next()
+    {
+        move_focus(focused, &next);
+        return;
+    }
+
+    let Some(from_op) = focused
+        .as_ref()
+        .and_then(|window| window.output())
+        .or_else(output::get_focused)
+    else {
+        return;
+    };
+    let Some(to_op) = output_in_direction(&from_op, dir) else {
+        return;
+    };
+    let from = focused
+        .as_ref()
+        .and_then(Rect::of_window)
+        .or_else(|| Rect::of_output(&from_op))
+        .map_or(Point { x: 0, y: 0 }, |rect| rect.center2());
+    // an output without windows still takes focus, so the next window spawns there
+    to_op.focus();
+    if let Some(next) = window_at_edge(&to_op, dir, from) {
+        next.set_focused(true);
+    }
+}
+
+/// swap the focused window with the window in `dir`, or move it to the neighbouring output, keeping its
+/// tag names, if there is none on this output.
+pub fn swap_in_direction(dir: Direction) {
+    let Some(focused) = window::get_focused() else {
+        return;
+    };
+    if let Some(next) = focused.in_direction(dir).next() {
+        swap_windows(&focused, &next);
+        return;
+    }
+    if let Some(to_op) = focused
+        .output()
+        .and_then(|from_op| output_in_direction(&from_op, dir))
+    {
+        move_to_output(&focused, &to_op);
+    }
+}
+
+/// enabled outputs ordered by their position, left to right and then top to bottom.
+fn outputs_by_position() -> Vec<OutputHandle> {
+    let mut outputs = output::get_all_enabled()
+        .map(|op| (op.loc().map(|Point { x, y }| (x, y)), op))
+        .collect::<Vec<_>>();
+    outputs.sort_by_key(|(loc, _)| *loc);
+    outputs.into_iter().map(|(_, op)| op).collect()
+}
+
+/// move the focused window to the next (`step = 1`) or previous (`step = -1`) output by position,
+/// keeping its tag names.
+pub fn move_to_adjacent_output(step: isize) {
+    let Some(focused) = window::get_focused() else {
+        return;
+    };
+    let Some(from_op) = focused.output() else {
+        return;
+    };
+    let outputs = outputs_by_position();
+    let Some(idx) = outputs.iter().position(|op| *op == from_op) else {
+        return;
+    };
+    let target = &outputs[(idx as isize + step).rem_euclid(outputs.len() as isize) as usize];
+    if *target != from_op {
+        move_to_output(&focused, target);
+    }
+}
+
+/// move `window` to the tags with the same names on `output`, and follow it there.
+fn move_to_output(window: &WindowHandle, output: &OutputHandle) {
+    let tag_names = window.tags().map(|tag| tag.name()).collect::<Vec<_>>();
+    retag(window, &tag_names, output);
+    output.focus();
+    window.set_focused(true);
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use itertools::Itertools;
+use pinnacle_api::output;
+use pinnacle_api::output::OutputHandle;
+use pinnacle_api::tag;
+use pinnacle_api::window;
+use pinnacle_api::window::WindowHandle;
+
+use crate::output_profiles::OutputInfo;
+
+/// a window moved off an output that went away, along with the names of the tags it had there.
+#[derive(Debug, Clone)]
+struct DisplacedWindow {
+    window: WindowHandle,
+    tag_names: Vec<String>,
+}
+
+/// moves windows off outputs that get disconnected and puts them back when the output returns.
+#[derive(Debug, Default)]
+pub struct Migrator {
+    /// output name -> [`OutputInfo::identity`] for every output seen connected. a disconnected output
+    /// can't be asked for its make, model and serial anymore.
+    identities: Mutex<HashMap<String, String>>,
+    /// displaced windows, keyed by the identity of the output they came from.
+    displaced: Mutex<HashMap<String, Vec<DisplacedWindow>>>,
+}
+
+/// move `window` to the tags named `tag_names` on `output`, or to the output's active tags if it has none
+/// of them.
+pub fn retag(window: &WindowHandle, tag_names: &[String], output: &OutputHandle) {
+    let tags = tag_names
+        .iter()
+        .filter_map(|name| tag::get_on_output(name, output))
+        .collect::<Vec<_>>();
+    if tags.is_empty() {
+        window.move_to_output(output);
+    } else {
+        window.set_tags(tags);
+    }
+}
+
+impl Migrator {
+    /// remember a newly connected output and move back any windows displaced from it. must be called
+    /// after the output's tags have been added.
+    pub fn connected(&self, output: &OutputHandle) {
+        let identity = OutputInfo::from_handle(output).identity();
+        self.identities
+            .lock()
+            .unwrap()
+            .insert(output.name(), identity.clone());
+
+        let Some(displaced) = self.displaced.lock().unwrap().remove(&identity) else {
+            return;
+        };
+        let alive = window::get_all().collect::<Vec<_>>();
+        for DisplacedWindow { window, tag_names } in displaced {
+            if alive.contains(&window) {
+                tracing::info!(app_id = %window.app_id(), output = %output.name(), "restoring window");
+                retag(&window, &tag_names, output);
+            }
+        }
+    }
+
+    /// move the windows of a disconnected output to the matching tags on a remaining output.
+    pub fn disconnected(&self, output: &OutputHandle) {
+        let output_name = output.name();
+        let Some(identity) = self.identities.lock().unwrap().get(&output_name).cloned() else {
+            tracing::warn!(output = %output_name, "disconnected output was never seen connected");
+            return;
+        };
+        let Some(target) = output::get_focused()
+            .filter(|op| op.name() != output_name)
+            .or_else(|| output::get_all().find(|op| op.name() != output_name))
+        else {
+            return;
+        };
+
+        let displaced = output
+            .tags()
+            .flat_map(|tag| tag.windows())
+            .unique()
+            .map(|window| {
+                let tag_names = window
+                    .tags()
+                    .filter(|tag| tag.output().name() == output_name)
+                    .map(|tag| tag.name())
+                    .collect::<Vec<_>>();
+                tracing::info!(app_id = %window.app_id(), output = %target.name(), "migrating window");
+                retag(&window, &tag_names, &target);
+                DisplacedWindow { window, tag_names }
+            })
+            .collect::<Vec<_>>();
+
+        if !displaced.is_empty() {
+            self.displaced
+                .lock()
+                .unwrap()
+                .entry(identity)
+                .or_default()
+                .extend(displaced);
+        }
+    }
+}
@@ -0,0 +1,450 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use pinnacle_api::output;
+use pinnacle_api::output::Mode;
+use pinnacle_api::output::OutputHandle;
+use pinnacle_api::output::Transform;
+use pinnacle_api::util::Point;
+use pinnacle_api::util::Size;
+
+use crate::config_file::OutputsConfig;
+
+/// the identifying properties of a connected output.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct OutputInfo {
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub serial: String,
+}
+
+impl OutputInfo {
+    pub fn from_handle(output: &OutputHandle) -> OutputInfo {
+        OutputInfo {
+            name: output.name(),
+            make: output.make(),
+            model: output.model(),
+            serial: output.serial(),
+        }
+    }
+
+    /// a stable identity for the physical monitor. the connector name stands in for the serial when the
+    /// monitor doesn't report one, so two identical monitors stay distinct.
+    pub fn identity(&self) -> String {
+        let serial = if self.serial.is_empty() {
+            &self.name
+        } else {
+            &self.serial
+        };
+        format!("{} {} {serial}", self.make, self.model)
+    }
+}
+
+/// criteria an output has to meet to be picked up by a profile entry. unset fields match anything.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct OutputMatcher {
+    pub name: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+}
+
+impl OutputMatcher {
+    pub fn matches(&self, info: &OutputInfo) -> bool {
+        let field = |want: &Option<String>, have: &str| want.as_deref().is_none_or(|w| w == have);
+        field(&self.name, &info.name)
+            && field(&self.make, &info.make)
+            && field(&self.model, &info.model)
+            && field(&self.serial, &info.serial)
+    }
+}
+
+/// a requested mode. the refresh rate is optional and matched to the closest available one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModeSpec {
+    pub size: Size,
+    pub refresh_hz: Option<f64>,
+}
+
+impl FromStr for ModeSpec {
+    type Err = String;
+
+    /// parses `WIDTHxHEIGHT` or `WIDTHxHEIGHT@HZ`, e.g. `2560x1440@143.97`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (size, refresh) = match s.split_once('@') {
+            Some((size, refresh)) => (size, Some(refresh)),
+            None => (s, None),
+        };
+        let (w, h) = size
+            .split_once('x')
+            .ok_or_else(|| format!("mode `{s}` should look like 2560x1440 or 2560x1440@144"))?;
+        let dim = |d: &str| {
+            d.trim()
+                .parse::<u32>()
+                .map_err(|err| format!("mode `{s}`: {err}"))
+        };
+        let refresh_hz = refresh
+            .map(|r| {
+                r.trim()
+                    .parse::<f64>()
+                    .map_err(|err| format!("mode `{s}`: {err}"))
+            })
+            .transpose()?;
+        Ok(ModeSpec {
+            size: Size {
+                w: dim(w)?,
+                h: dim(h)?,
+            },
+            refresh_hz,
+        })
+    }
+}
+
+impl ModeSpec {
+    /// pick the available mode closest to this spec.
+    pub fn pick(&self, modes: impl IntoIterator<Item = Mode>) -> Option<Mode> {
+        let modes = modes.into_iter().filter(|mode| mode.size == self.size);
+        match self.refresh_hz {
+            Some(hz) => {
+                let mhz = hz * 1000.0;
+                modes.min_by(|m1, m2| {
+                    let d1 = (m1.refresh_rate_mhz as f64 - mhz).abs();
+                    let d2 = (m2.refresh_rate_mhz as f64 - mhz).abs();
+                    d1.total_cmp(&d2)
+                })
+            }
+            None => modes.max_by_key(|mode| mode.refresh_rate_mhz),
+        }
+    }
+}
+
+/// per-output display settings. anything unset falls back to the global `[outputs]` settings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputSettings {
+    pub mode: Option<ModeSpec>,
+    pub scale: Option<f32>,
+    pub transform: Option<Transform>,
+    pub position: Option<Point>,
+    pub vrr: Option<output::Vrr>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProfileOutput {
+    pub matcher: OutputMatcher,
+    pub settings: OutputSettings,
+}
+
+/// a named set of outputs. like kanshi, a profile is only selected when every connected output is
+/// claimed by exactly one of its entries.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub outputs: Vec<ProfileOutput>,
+}
+
+impl Profile {
+    /// assign each connected output to a profile entry, returning the entry index per output.
+    pub fn assign(&self, connected: &[OutputInfo]) -> Option<Vec<usize>> {
+        fn go(
+            profile: &Profile,
+            connected: &[OutputInfo],
+            taken: &mut Vec<bool>,
+            assignment: &mut Vec<usize>,
+        ) -> bool {
+            let Some(info) = connected.get(assignment.len()) else {
+                return true;
+            };
+            for (idx, entry) in profile.outputs.iter().enumerate() {
+                if taken[idx] || !entry.matcher.matches(info) {
+                    continue;
+                }
+                taken[idx] = true;
+                assignment.push(idx);
+                if go(profile, connected, taken, assignment) {
+                    return true;
+                }
+                assignment.pop();
+                taken[idx] = false;
+            }
+            false
+        }
+
+        if self.outputs.len() != connected.len() {
+            return None;
+        }
+        let mut taken = vec![false; self.outputs.len()];
+        let mut assignment = Vec::with_capacity(connected.len());
+        go(self, connected, &mut taken, &mut assignment).then_some(assignment)
+    }
+}
+
+/// find the first profile matching the connected outputs. returns the profile along with the settings
+/// for each connected output, in the same order as `connected`.
+pub fn select_profile<'a>(
+    profiles: &'a [Profile],
+    connected: &[OutputInfo],
+) -> Option<(&'a Profile, Vec<&'a OutputSettings>)> {
+    profiles.iter().find_map(|profile| {
+        let assignment = profile.assign(connected)?;
+        let settings = assignment
+            .into_iter()
+            .map(|idx| &profile.outputs[idx].settings)
+            .collect();
+        Some((profile, settings))
+    })
+}
+
+fn size_cmp(size1: &Size, size2: &Size) -> Ordering {
+    (size1.w * size1.h).cmp(&(size2.w * size2.h))
+}
+
+fn mode_cmp(mode1: &Mode, mode2: &Mode) -> Ordering {
+    size_cmp(&mode1.size, &mode2.size).then(mode1.refresh_rate_mhz.cmp(&mode2.refresh_rate_mhz))
+}
+
+/// the largest, fastest mode that fits within `max`.
+pub fn largest_mode(modes: impl IntoIterator<Item = Mode>, max: Size) -> Option<Mode> {
+    modes
+        .into_iter()
+        .filter(|mode| mode.size.w <= max.w && mode.size.h <= max.h)
+        .max_by(mode_cmp)
+}
+
+fn apply_settings(output: &OutputHandle, settings: &OutputSettings, defaults: &OutputsConfig) {
+    let output_name = output.name();
+
+    let mode = settings
+        .mode
+        .and_then(|spec| {
+            let mode = spec.pick(output.modes());
+            if mode.is_none() {
+                tracing::warn!(output = %output_name, ?spec, "requested mode is not available");
+            }
+            mode
+        })
+        .or_else(|| largest_mode(output.modes(), defaults.max_mode));
+    if let Some(Mode {
+        size: Size { w, h },
+        refresh_rate_mhz,
+    }) = mode
+    {
+        tracing::info!(output = %output_name, "setting mode {w}x{h}@{refresh_rate_mhz}");
+        output.set_mode(w, h, refresh_rate_mhz);
+    }
+
+    output.set_scale(settings.scale.unwrap_or(defaults.scale));
+    output.set_transform(settings.transform.unwrap_or(Transform::Normal));
+    output.set_vrr(settings.vrr.unwrap_or(defaults.vrr));
+    if let Some(Point { x, y }) = settings.position {
+        output.set_loc(x, y);
+    }
+}
+
+/// configure every connected output, using the first profile matching the set of connected outputs and
+/// the global settings for everything else.
+///
+/// returns whether the profile positioned every output, in which case there's nothing left to arrange.
+pub fn configure_outputs(config: &OutputsConfig) -> bool {
+    let outputs = output::get_all().collect::<Vec<_>>();
+    let infos = outputs
+        .iter()
+        .map(OutputInfo::from_handle)
+        .collect::<Vec<_>>();
+
+    let fallback = OutputSettings::default();
+    let settings = match select_profile(&config.profiles, &infos) {
+        Some((profile, settings)) => {
+            tracing::info!(profile = %profile.name, "applying output profile");
+            settings
+        }
+        None => {
+            tracing::info!("no output profile matches, using the default output settings");
+            vec![&fallback; outputs.len()]
+        }
+    };
+
+    let positioned = settings.iter().all(|settings| settings.position.is_some());
+    for (output, settings) in outputs.iter().zip(settings) {
+        apply_settings(output, settings, config);
+    }
+    positioned
+}
+
+/// parse a transform as written in the config file, e.g. `90` or `flipped-270`.
+pub fn parse_transform(s: &str) -> Result<Transform, String> {
+    Ok(match s {
+        "normal" | "0" => Transform::Normal,
+        "90" => Transform::_90,
+        "180" => Transform::_180,
+        "270" => Transform::_270,
+        "flipped" => Transform::Flipped,
+        "flipped-90" => Transform::Flipped90,
+        "flipped-180" => Transform::Flipped180,
+        "flipped-270" => Transform::Flipped270,
+        other => return Err(format!("unknown transform `{other}`")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str, make: &str, model: &str, serial: &str) -> OutputInfo {
+        OutputInfo {
+            name: name.to_owned(),
+            make: make.to_owned(),
+            model: model.to_owned(),
+            serial: serial.to_owned(),
+        }
+    }
+
+    fn profile(matchers: impl IntoIterator<Item = OutputMatcher>) -> Profile {
+        Profile {
+            name: "test".to_owned(),
+            outputs: matchers
+                .into_iter()
+                .map(|matcher| ProfileOutput {
+                    matcher,
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn by_name(name: &str) -> OutputMatcher {
+        OutputMatcher {
+            name: Some(name.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn mode(w: u32, h: u32, refresh_rate_mhz: u32) -> Mode {
+        Mode {
+            size: Size { w, h },
+            refresh_rate_mhz,
+        }
+    }
+
+    #[test]
+    fn assign_backtracks_when_a_broad_entry_takes_the_wrong_output() {
+        let profile = profile([
+            OutputMatcher {
+                make: Some("Dell".to_owned()),
+                ..Default::default()
+            },
+            OutputMatcher {
+                serial: Some("X".to_owned()),
+                ..Default::default()
+            },
+        ]);
+        let connected = [
+            info("DP-1", "Dell", "U2720Q", "X"),
+            info("DP-2", "Dell", "U2720Q", "Y"),
+        ];
+        assert_eq!(profile.assign(&connected), Some(vec![1, 0]));
+    }
+
+    #[test]
+    fn assign_tells_identical_monitors_apart_by_connector() {
+        let profile = profile([by_name("DP-1"), by_name("DP-2")]);
+        let connected = [
+            info("DP-2", "Dell", "U2720Q", ""),
+            info("DP-1", "Dell", "U2720Q", ""),
+        ];
+        assert_eq!(profile.assign(&connected), Some(vec![1, 0]));
+        assert_ne!(connected[0].identity(), connected[1].identity());
+    }
+
+    #[test]
+    fn assign_needs_every_output_claimed_exactly_once() {
+        let connected = [info("DP-1", "Dell", "U2720Q", "X")];
+        // more entries than outputs
+        assert_eq!(
+            profile([by_name("DP-1"), by_name("HDMI-A-1")]).assign(&connected),
+            None
+        );
+        // more outputs than entries
+        let two = [connected[0].clone(), info("eDP-1", "BOE", "0x095F", "")];
+        assert_eq!(profile([by_name("DP-1")]).assign(&two), None);
+        // two entries that can only take the same output
+        assert_eq!(
+            profile([by_name("DP-1"), by_name("DP-1")]).assign(&two),
+            None
+        );
+        assert_eq!(profile([by_name("DP-1")]).assign(&connected), Some(vec![0]));
+    }
+
+    #[test]
+    fn select_profile_takes_the_first_match() {
+        let mut docked = profile([by_name("DP-1"), by_name("eDP-1")]);
+        docked.name = "docked".to_owned();
+        docked.outputs[1].settings.scale = Some(1.5);
+        let mut laptop = profile([by_name("eDP-1")]);
+        laptop.name = "laptop".to_owned();
+        let profiles = [docked, laptop];
+
+        let connected = [info("eDP-1", "BOE", "0x095F", "")];
+        let (selected, settings) = select_profile(&profiles, &connected).unwrap();
+        assert_eq!(selected.name, "laptop");
+        assert_eq!(settings, [&OutputSettings::default()]);
+
+        let connected = [connected[0].clone(), info("DP-1", "Dell", "U2720Q", "X")];
+        let (selected, settings) = select_profile(&profiles, &connected).unwrap();
+        assert_eq!(selected.name, "docked");
+        assert_eq!(settings[0].scale, Some(1.5));
+        assert_eq!(settings[1].scale, None);
+    }
+
+    #[test]
+    fn mode_spec_parses() {
+        assert_eq!(
+            "2560x1440@143.97".parse::<ModeSpec>(),
+            Ok(ModeSpec {
+                size: Size { w: 2560, h: 1440 },
+                refresh_hz: Some(143.97),
+            })
+        );
+        assert_eq!(
+            "1920x1080".parse::<ModeSpec>(),
+            Ok(ModeSpec {
+                size: Size { w: 1920, h: 1080 },
+                refresh_hz: None,
+            })
+        );
+        assert_eq!(
+            "1920 x 1080 @ 60"
+                .parse::<ModeSpec>()
+                .map(|spec| spec.refresh_hz),
+            Ok(Some(60.0))
+        );
+        for invalid in [
+            "1920",
+            "1920x",
+            "x1080",
+            "1920x1080@",
+            "1920x1080@fast",
+            "-1x1080",
+        ] {
+            assert!(invalid.parse::<ModeSpec>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn mode_spec_picks_the_closest_refresh_rate() {
+        let modes = [
+            mode(2560, 1440, 59951),
+            mode(2560, 1440, 143970),
+            mode(2560, 1440, 120000),
+            mode(1920, 1080, 240000),
+        ];
+        let pick = |spec: &str| spec.parse::<ModeSpec>().unwrap().pick(modes);
+        assert_eq!(pick("2560x1440@144"), Some(modes[1]));
+        assert_eq!(pick("2560x1440@60"), Some(modes[0]));
+        assert_eq!(pick("2560x1440@100"), Some(modes[2]));
+        // without a refresh rate the fastest mode of that size is taken
+        assert_eq!(pick("2560x1440"), Some(modes[1]));
+        assert_eq!(pick("1920x1080@60"), Some(modes[3]));
+        assert_eq!(pick("3840x2160"), None);
+    }
+}
@@ -0,0 +1,43 @@
+use std::fs;
+
+/// the parent of process `pid`, from `/proc/<pid>/stat`.
+pub fn parent(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // the command name is in parentheses and may contain anything, spaces and parentheses included, so
+    // the fields are counted from the last `)`: the state, then the parent pid
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// the ancestors of process `pid`, its parent first, up to but excluding init.
+pub fn ancestors(pid: u32) -> Vec<u32> {
+    let mut ancestors = Vec::new();
+    let mut current = pid;
+    while let Some(parent) = parent(current) {
+        // pid 0 is the kernel, and a parent that was already seen means the process tree changed under us
+        if parent <= 1 || parent == pid || ancestors.contains(&parent) {
+            break;
+        }
+        ancestors.push(parent);
+        current = parent;
+    }
+    ancestors
+}
+
+/// the value of the environment variable `name` that process `pid` was started with.
+pub fn env_var(pid: u32, name: &str) -> Option<String> {
+    let environ = fs::read(format!("/proc/{pid}/environ")).ok()?;
+    environ.split(|&b| b == 0).find_map(|var| {
+        let value = var.strip_prefix(name.as_bytes())?.strip_prefix(b"=")?;
+        Some(String::from_utf8_lossy(value).into_owned())
+    })
+}
+
+/// the cgroup v2 path of process `pid`, like `/user.slice/user-1000.slice/user@1000.service/app.slice/...`.
+pub fn cgroup(pid: u32) -> Option<String> {
+    let cgroups = fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?;
+    cgroups
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(str::to_owned)
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use pinnacle_api::input::Mod;
+use pinnacle_api::output;
+use pinnacle_api::output::OutputHandle;
+use pinnacle_api::tag;
+use pinnacle_api::util::Point;
+use pinnacle_api::util::Size;
+use pinnacle_api::window;
+use pinnacle_api::window::WindowHandle;
+
+use crate::uwsm_command::UwsmCommand;
+use crate::window_rules::Matcher;
+use crate::window_rules::WindowInfo;
+
+/// the tag hidden scratchpads and swallowed terminals are parked on. it's added to every output but never
+/// bound to a key, so it is never active.
+pub const HIDDEN_TAG: &str = "scratchpad";
+
+/// a window that is toggled in and out of view with a keybind, floating over whatever tag is active.
+#[derive(Debug, Clone)]
+pub struct Scratchpad {
+    pub name: String,
+    pub mods: Mod,
+    pub key: String,
+    /// spawned through `uwsm app` when the scratchpad is toggled and its window isn't around.
+    pub command: Vec<String>,
+    /// how the scratchpad's window is recognized when it's mapped. all of them have to match.
+    pub matchers: Vec<Matcher>,
+    /// the size of the window as a fraction of the focused output.
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Scratchpad {
+    pub fn matches(&self, info: &WindowInfo) -> bool {
+        self.matchers.iter().all(|m| m.matches(info))
+    }
+
+    /// the geometry of the scratchpad when centered in an output at `loc` with the logical size `size`.
+    pub fn centered_in(&self, loc: Point, size: Size) -> (Point, Size) {
+        let w = (size.w as f32 * self.width).round() as u32;
+        let h = (size.h as f32 * self.height).round() as u32;
+        let x = loc.x + (size.w.saturating_sub(w) / 2) as i32;
+        let y = loc.y + (size.h.saturating_sub(h) / 2) as i32;
+        (Point { x, y }, Size { w, h })
+    }
+}
+
+/// keeps track of the window belonging to each scratchpad.
+#[derive(Debug)]
+pub struct Scratchpads {
+    pads: Vec<Scratchpad>,
+    /// scratchpad name -> its window, once it has been mapped.
+    windows: Mutex<HashMap<String, WindowHandle>>,
+}
+
+fn alive(window: &WindowHandle) -> bool {
+    window::get_all().any(|w| w == *window)
+}
+
+/// whether `window` is parked on the hidden tag.
+pub fn is_hidden(window: &WindowHandle) -> bool {
+    window.tags().any(|tag| tag.name() == HIDDEN_TAG)
+}
+
+impl Scratchpads {
+    pub fn new(pads: Vec<Scratchpad>) -> Scratchpads {
+        Scratchpads {
+            pads,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn pads(&self) -> &[Scratchpad] {
+        &self.pads
+    }
+
+    /// the live window of the scratchpad named `name`, if it has one.
+    fn window(&self, name: &str) -> Option<WindowHandle> {
+        let mut windows = self.windows.lock().unwrap();
+        match windows.get(name) {
+            Some(window) if alive(window) => Some(window.clone()),
+            Some(_) => {
+                windows.remove(name);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// claim `window` for the first scratchpad it matches that doesn't have a window yet, showing it on
+    /// the focused output unless it was already hidden. returns whether the window is a scratchpad, in
+    /// which case the regular window rules shouldn't touch it.
+    pub fn claim(&self, window: &WindowHandle) -> bool {
+        let info = WindowInfo::from_handle(window);
+        let mut windows = self.windows.lock().unwrap();
+        let Some(pad) = self.pads.iter().find(|pad| {
+            pad.matches(&info)
+                && windows
+                    .get(&pad.name)
+                    .is_none_or(|claimed| claimed == window || !alive(claimed))
+        }) else {
+            return false;
+        };
+        tracing::info!(scratchpad = %pad.name, app_id = %info.app_id, "claiming scratchpad window");
+        windows.insert(pad.name.clone(), window.clone());
+        drop(windows);
+
+        window.set_floating(true);
+        if !is_hidden(window)
+            && let Some(output) = output::get_focused()
+        {
+            show(pad, window, &output);
+        }
+        true
+    }
+
+    /// show the scratchpad named `name` on the focused output, hide it if it's already shown there, or
+    /// spawn it if it isn't running.
+    pub fn toggle(&self, name: &str) {
+        let Some(pad) = self.pads.iter().find(|pad| pad.name == name) else {
+            tracing::warn!(scratchpad = %name, "unknown scratchpad");
+            return;
+        };
+        let Some(window) = self.window(name) else {
+            let Some((program, args)) = pad.command.split_first() else {
+                return;
+            };
+            tracing::info!(scratchpad = %name, "spawning scratchpad");
+            UwsmCommand::new(program).args(args).spawn();
+            return;
+        };
+        let Some(focused_op) = output::get_focused() else {
+            return;
+        };
+
+        let shown_here = !is_hidden(&window)
+            && window.is_on_active_tag()
+            && window.output().is_some_and(|op| op == focused_op);
+        if shown_here {
+            hide(&window);
+        } else {
+            show(pad, &window, &focused_op);
+        }
+    }
+}
+
+/// float the window over the active tags of `output`, centered, and focus it.
+fn show(pad: &Scratchpad, window: &WindowHandle, output: &OutputHandle) {
+    window.set_tags(output.active_tags());
+    window.set_floating(true);
+    if let (Some(loc), Some(size)) = (output.loc(), output.logical_size()) {
+        let (Point { x, y }, Size { w, h }) = pad.centered_in(loc, size);
+        window.set_geometry(x, y, w, h);
+    }
+    window.raise();
+    window.set_focused(true);
+}
+
+/// park the window on the hidden tag of its output.
+pub fn hide(window: &WindowHandle) {
+    let Some(hidden_tag) = window
+        .output()
+        .and_then(|op| tag::get_on_output(HIDDEN_TAG, &op))
+    else {
+        return;
+    };
+    window.move_to_tag(&hidden_tag);
+}
@@ -0,0 +1,195 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use pinnacle_api::output;
+use pinnacle_api::tag;
+use pinnacle_api::window;
+use pinnacle_api::window::LayoutMode;
+use pinnacle_api::window::WindowHandle;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::scratchpads;
+use crate::state;
+
+const STATE_FILE: &str = "session.toml";
+
+/// how long after the config starts windows are matched against the saved session. apps that map later
+/// than this were most likely opened anew, not restored.
+const RESTORE_WINDOW: Duration = Duration::from_secs(120);
+
+/// how often the session is saved besides on quit and reload, so a crash, or a quit that the compositor
+/// carries out before the save is written, doesn't lose all of it. this is longer than [`RESTORE_WINDOW`],
+/// so a periodic save never catches a session that is still being restored.
+const SAVE_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SavedLayoutMode {
+    Tiled,
+    Floating,
+    Maximized,
+    Fullscreen,
+}
+
+impl From<LayoutMode> for SavedLayoutMode {
+    fn from(mode: LayoutMode) -> Self {
+        match mode {
+            LayoutMode::Tiled | LayoutMode::Spilled => SavedLayoutMode::Tiled,
+            LayoutMode::Floating => SavedLayoutMode::Floating,
+            LayoutMode::Maximized => SavedLayoutMode::Maximized,
+            LayoutMode::Fullscreen => SavedLayoutMode::Fullscreen,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SavedGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32,
+}
+
+/// where a window was and how it was laid out, as stored on disk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedWindow {
+    pub app_id: String,
+    pub title: String,
+    pub output: String,
+    pub tags: Vec<String>,
+    pub layout_mode: SavedLayoutMode,
+    /// only kept for floating windows, tiles are placed by the layout.
+    pub geometry: Option<SavedGeometry>,
+}
+
+impl SavedWindow {
+    fn of(window: &WindowHandle) -> Option<SavedWindow> {
+        let output = window.output()?;
+        let layout_mode = SavedLayoutMode::from(window.layout_mode());
+        let geometry = match (layout_mode, window.loc(), window.size()) {
+            (SavedLayoutMode::Floating, Some(loc), Some(size)) => Some(SavedGeometry {
+                x: loc.x,
+                y: loc.y,
+                w: size.w,
+                h: size.h,
+            }),
+            _ => None,
+        };
+        Some(SavedWindow {
+            app_id: window.app_id(),
+            title: window.title(),
+            output: output.name(),
+            tags: window.tags().map(|tag| tag.name()).collect(),
+            layout_mode,
+            geometry,
+        })
+    }
+
+    /// put `window` back where this was.
+    fn apply(&self, window: &WindowHandle) {
+        let Some(output) = output::get_by_name(&self.output) else {
+            return;
+        };
+        let tags = self
+            .tags
+            .iter()
+            .filter_map(|name| tag::get_on_output(name, &output))
+            .collect::<Vec<_>>();
+        if !tags.is_empty() {
+            window.set_tags(tags);
+        }
+        // a window rule or the client may have maximized or fullscreened the window already, which would
+        // keep it out of the tiled or floating layout it was saved in
+        match self.layout_mode {
+            SavedLayoutMode::Tiled => {
+                window.set_maximized(false);
+                window.set_fullscreen(false);
+                window.set_floating(false);
+            }
+            SavedLayoutMode::Floating => {
+                window.set_maximized(false);
+                window.set_fullscreen(false);
+                window.set_floating(true);
+                if let Some(SavedGeometry { x, y, w, h }) = self.geometry {
+                    window.set_geometry(x, y, w, h);
+                }
+            }
+            SavedLayoutMode::Maximized => window.set_maximized(true),
+            SavedLayoutMode::Fullscreen => window.set_fullscreen(true),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedSession {
+    #[serde(default)]
+    pub windows: Vec<SavedWindow>,
+}
+
+/// saves where windows are, and puts the windows of the next session back there as they're mapped.
+#[derive(Debug)]
+pub struct Session {
+    /// saved windows that no window has been matched to yet.
+    pending: Mutex<Vec<SavedWindow>>,
+    started: Instant,
+}
+
+impl Session {
+    pub fn load() -> Session {
+        Session {
+            pending: Mutex::new(state::load::<SavedSession>(STATE_FILE).windows),
+            started: Instant::now(),
+        }
+    }
+
+    fn restoring(&self) -> bool {
+        self.started.elapsed() < RESTORE_WINDOW
+    }
+
+    /// place `window` like the saved window it matches, preferring one with the same title over one with
+    /// just the same app_id. each saved window is only restored once.
+    pub fn restore(&self, window: &WindowHandle) {
+        if !self.restoring() {
+            return;
+        }
+        let app_id = window.app_id();
+        let title = window.title();
+        let saved = {
+            let mut pending = self.pending.lock().unwrap();
+            let Some(idx) = pending
+                .iter()
+                .position(|saved| saved.app_id == app_id && saved.title == title)
+                .or_else(|| pending.iter().position(|saved| saved.app_id == app_id))
+            else {
+                return;
+            };
+            pending.remove(idx)
+        };
+        tracing::info!(%app_id, output = %saved.output, tags = ?saved.tags, "restoring window");
+        saved.apply(window);
+    }
+
+    /// write the current windows to disk. saved windows that are still waiting to be restored are kept,
+    /// so saving right after a restart doesn't lose the apps that are still starting up.
+    pub fn save(&self) {
+        let mut windows = window::get_all()
+            .filter(|window| !scratchpads::is_hidden(window))
+            .filter_map(|window| SavedWindow::of(&window))
+            .collect::<Vec<_>>();
+        if self.restoring() {
+            windows.extend(self.pending.lock().unwrap().iter().cloned());
+        }
+        tracing::debug!(windows = windows.len(), "saving session");
+        state::save(STATE_FILE, &SavedSession { windows });
+    }
+
+    /// save the session every [`SAVE_INTERVAL`].
+    pub async fn save_periodically(&self) {
+        loop {
+            tokio::time::sleep(SAVE_INTERVAL).await;
+            self.save();
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// `$XDG_STATE_HOME/pinnacle`, falling back to `~/.local/state/pinnacle`.
+pub fn state_dir() -> PathBuf {
+    env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("pinnacle")
+}
+
+/// load a state file from [`state_dir`]. missing or unreadable state yields the default, since state is
+/// only ever a convenience.
+pub fn load<T: DeserializeOwned + Default>(file_name: &str) -> T {
+    let path = state_dir().join(file_name);
+    match fs::read_to_string(&path) {
+        Ok(contents) => toml::from_str(&contents).unwrap_or_else(|err| {
+            tracing::warn!(path = %path.display(), "ignoring invalid state file: {err}");
+            T::default()
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => T::default(),
+        Err(err) => {
+            tracing::warn!(path = %path.display(), "failed to read state file: {err}");
+            T::default()
+        }
+    }
+}
+
+/// write a state file to [`state_dir`], replacing it atomically.
+pub fn save<T: Serialize>(file_name: &str, value: &T) {
+    let dir = state_dir();
+    let path = dir.join(file_name);
+    let tmp = dir.join(format!(".{file_name}.tmp"));
+    let res = toml::to_string(value)
+        .map_err(std::io::Error::other)
+        .and_then(|contents| {
+            fs::create_dir_all(&dir)?;
+            fs::write(&tmp, contents)?;
+            fs::rename(&tmp, &path)
+        });
+    if let Err(err) = res {
+        tracing::warn!(path = %path.display(), "failed to write state file: {err}");
+    }
+}
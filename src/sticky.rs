@@ -0,0 +1,63 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use itertools::Itertools;
+use pinnacle_api::tag::TagHandle;
+use pinnacle_api::window;
+use pinnacle_api::window::WindowHandle;
+
+/// windows that stay visible on whatever tag is active on their output, like a picture-in-picture video.
+#[derive(Debug, Default)]
+pub struct Sticky {
+    windows: Mutex<HashSet<WindowHandle>>,
+}
+
+impl Sticky {
+    pub fn is_sticky(&self, window: &WindowHandle) -> bool {
+        self.windows.lock().unwrap().contains(window)
+    }
+
+    /// make `window` sticky, or not anymore. returns whether it's sticky now.
+    pub fn toggle(&self, window: &WindowHandle) -> bool {
+        let mut windows = self.windows.lock().unwrap();
+        if windows.remove(window) {
+            tracing::info!(app_id = %window.app_id(), "window is no longer sticky");
+            false
+        } else {
+            tracing::info!(app_id = %window.app_id(), "window is sticky");
+            windows.insert(window.clone());
+            true
+        }
+    }
+
+    pub fn forget(&self, window: &WindowHandle) {
+        self.windows.lock().unwrap().remove(window);
+    }
+
+    /// put the sticky windows on the output of `tag` on it, along with the other active tags there. this
+    /// is fed from `TagSignal::Active`, so it follows the tag binds, `switch_to` and anything else that
+    /// activates tags.
+    pub fn tag_activated(&self, tag: &TagHandle) {
+        let output = tag.output();
+        let sticky = {
+            let alive = window::get_all().collect::<Vec<_>>();
+            let mut windows = self.windows.lock().unwrap();
+            windows.retain(|window| alive.contains(window));
+            windows
+                .iter()
+                .filter(|window| window.output().is_some_and(|op| op == output))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        if sticky.is_empty() {
+            return;
+        }
+        let tags = std::iter::once(tag.clone())
+            .chain(output.active_tags())
+            .unique()
+            .collect::<Vec<_>>();
+        for window in sticky {
+            window.set_tags(tags.clone());
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use pinnacle_api::process::Child;
+use pinnacle_api::process::Command;
+use pinnacle_api::window;
+use tokio::time::sleep;
+use tokio::time::timeout;
+
+use crate::uwsm_command::UwsmCommand;
+
+/// the wait before the second attempt. it doubles with every failed attempt after that, up to
+/// [`MAX_BACKOFF`].
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// failed attempts after which the user is told that a service has trouble starting.
+const NOTIFY_AFTER: u32 = 3;
+
+/// how often socket files and windows are looked for.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// how a supervised process shows that it's up.
+#[derive(Debug, Clone)]
+pub enum Readiness {
+    /// the process was spawned, or didn't need to be.
+    Spawned,
+    /// the process exits successfully, or is still running after `grace`. meant for clients that fail
+    /// right away when their server isn't up yet.
+    Exit { grace: Duration },
+    /// the file at `path`, usually a socket, exists within `timeout`.
+    Socket { path: PathBuf, timeout: Duration },
+    /// a window with `app_id` is mapped within `timeout`.
+    Window { app_id: String, timeout: Duration },
+}
+
+/// what came of spawning a service's process.
+pub enum Spawn {
+    Child(Child),
+    /// not spawned because the command is `once` or `unique` and has already been spawned.
+    Suppressed,
+    Failed,
+}
+
+impl Spawn {
+    /// the outcome of a spawn that returned `child`. pinnacle doesn't say why nothing was spawned, so for
+    /// a command that is `once` or `unique` it's taken to be suppressed, and for any other command to have
+    /// failed.
+    pub fn of(child: Option<Child>, once_or_unique: bool) -> Spawn {
+        match child {
+            Some(child) => Spawn::Child(child),
+            None if once_or_unique => Spawn::Suppressed,
+            None => Spawn::Failed,
+        }
+    }
+}
+
+impl Readiness {
+    /// wait for the process from `spawn` to be ready. a command that wasn't spawned because it's `unique`
+    /// or `once` is already running, so it's ready as far as its exit goes. a failed spawn is never ready.
+    async fn probe(&self, spawn: Spawn) -> bool {
+        match (self, spawn) {
+            (_, Spawn::Failed) => false,
+            (Readiness::Spawned, _) => true,
+            (Readiness::Exit { grace }, Spawn::Child(child)) => {
+                match timeout(*grace, child.wait_async()).await {
+                    Ok(exit) => exit.exit_code == Some(0),
+                    Err(_) => true,
+                }
+            }
+            (Readiness::Exit { .. }, Spawn::Suppressed) => true,
+            (Readiness::Socket { path, timeout }, _) => poll(*timeout, || path.exists()).await,
+            (Readiness::Window { app_id, timeout }, _) => {
+                poll(*timeout, || {
+                    window::get_all().any(|w| w.app_id() == *app_id)
+                })
+                .await
+            }
+        }
+    }
+}
+
+/// check `ready` until it holds or `limit` has passed.
+async fn poll(limit: Duration, ready: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + limit;
+    loop {
+        if ready() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// a process that should be up, with how to start it and how to tell that it is.
+pub struct Service {
+    name: String,
+    spawn: Box<dyn Fn() -> Spawn + Send + Sync>,
+    readiness: Readiness,
+    max_attempts: u32,
+}
+
+impl Service {
+    /// a service spawned through `uwsm app`. retries spawn the command again, so commands probed for a
+    /// socket or a window should be `unique`.
+    pub fn new(name: impl ToString, command: UwsmCommand, readiness: Readiness) -> Service {
+        Service::with_spawn(name, readiness, move || {
+            Spawn::of(command.clone().spawn(), command.is_once_or_unique())
+        })
+    }
+
+    /// a service spawned directly, for commands that manage their own unit, like `systemctl`.
+    pub fn plain(
+        name: impl ToString,
+        command: impl IntoIterator<Item = impl ToString>,
+        readiness: Readiness,
+    ) -> Service {
+        let command = command
+            .into_iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        Service::with_spawn(name, readiness, move || {
+            let Some((program, args)) = command.split_first() else {
+                return Spawn::Failed;
+            };
+            Spawn::of(Command::new(program).args(args).spawn(), false)
+        })
+    }
+
+    /// a service started by calling `spawn`.
+    pub fn with_spawn(
+        name: impl ToString,
+        readiness: Readiness,
+        spawn: impl Fn() -> Spawn + Send + Sync + 'static,
+    ) -> Service {
+        Service {
+            name: name.to_string(),
+            spawn: Box::new(spawn),
+            readiness,
+            max_attempts: 10,
+        }
+    }
+
+    /// give up after this many attempts. 10 by default.
+    pub fn max_attempts(self, max_attempts: u32) -> Service {
+        Service {
+            max_attempts: max_attempts.max(1),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Status {
+    /// waiting for attempt `attempt` to be ready, or to be made.
+    Starting {
+        attempt: u32,
+    },
+    Ready,
+    /// gave up after `attempts` attempts.
+    Failed {
+        attempts: u32,
+    },
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Starting { attempt } => write!(f, "starting (attempt {attempt})"),
+            Status::Ready => write!(f, "ready"),
+            Status::Failed { attempts } => write!(f, "failed after {attempts} attempts"),
+        }
+    }
+}
+
+/// starts services and retries them with exponential backoff until they're ready, keeping track of how
+/// each one is doing.
+#[derive(Debug, Default)]
+pub struct Supervisor {
+    statuses: Mutex<BTreeMap<String, Status>>,
+}
+
+impl Supervisor {
+    fn set_status(&self, name: &str, status: Status) {
+        self.statuses
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), status);
+    }
+
+    /// the status of the service called `name`, or `None` if it was never run.
+    pub fn status(&self, name: &str) -> Option<Status> {
+        self.statuses.lock().unwrap().get(name).copied()
+    }
+
+    /// the status of every service that was run, by name.
+    pub fn statuses(&self) -> BTreeMap<String, Status> {
+        self.statuses.lock().unwrap().clone()
+    }
+
+    /// show the status of every service in a desktop notification.
+    pub fn show_statuses(&self) {
+        let statuses = self.statuses();
+        let body = if statuses.is_empty() {
+            "no services were started".to_owned()
+        } else {
+            statuses
+                .iter()
+                .map(|(name, status)| format!("{name}: {status}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        notify("normal", "services", &body);
+    }
+
+    /// start `service` and wait for it to be ready, retrying until it is or it runs out of attempts.
+    /// returns the final status. a service that is already being started isn't started twice.
+    pub async fn run(&self, service: Service) -> Status {
+        let Service {
+            name,
+            spawn,
+            readiness,
+            max_attempts,
+        } = service;
+        {
+            let mut statuses = self.statuses.lock().unwrap();
+            if let Some(&status @ Status::Starting { .. }) = statuses.get(&name) {
+                tracing::debug!(service = %name, "service is already starting");
+                return status;
+            }
+            statuses.insert(name.clone(), Status::Starting { attempt: 1 });
+        }
+
+        let mut backoff = INITIAL_BACKOFF;
+        for attempt in 1..=max_attempts {
+            self.set_status(&name, Status::Starting { attempt });
+            if readiness.probe(spawn()).await {
+                tracing::info!(service = %name, attempt, "service is ready");
+                self.set_status(&name, Status::Ready);
+                return Status::Ready;
+            }
+            tracing::warn!(service = %name, attempt, ?readiness, "service isn't ready");
+            if attempt == NOTIFY_AFTER && attempt < max_attempts {
+                notify(
+                    "critical",
+                    &format!("{name} is having trouble starting"),
+                    "still retrying",
+                );
+            }
+            if attempt < max_attempts {
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+
+        tracing::error!(service = %name, attempts = max_attempts, "giving up on service");
+        let status = Status::Failed {
+            attempts: max_attempts,
+        };
+        self.set_status(&name, status);
+        notify(
+            "critical",
+            &format!("{name} failed to start"),
+            &status.to_string(),
+        );
+        status
+    }
+}
+
+/// show a desktop notification with `urgency`, one of `low`, `normal` and `critical`.
+pub fn notify(urgency: &str, summary: &str, body: &str) {
+    Command::new("notify-send")
+        .args([
+            &format!("--urgency={urgency}"),
+            "--app-name=pinnacle",
+            summary,
+            body,
+        ])
+        .spawn();
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use pinnacle_api::tag::TagHandle;
+use pinnacle_api::window;
+use pinnacle_api::window::WindowHandle;
+
+use crate::process_tree;
+use crate::scratchpads;
+use crate::window_rules::Matcher;
+use crate::window_rules::WindowInfo;
+
+/// which windows are terminals that get swallowed by the windows started from them.
+#[derive(Debug, Clone, Default)]
+pub struct SwallowConfig {
+    /// each terminal is recognized by a set of matchers that all have to match.
+    pub terminals: Vec<Vec<Matcher>>,
+    /// app_ids of windows that never swallow their terminal.
+    pub exclude: Vec<String>,
+}
+
+impl SwallowConfig {
+    fn is_terminal(&self, info: &WindowInfo) -> bool {
+        self.terminals
+            .iter()
+            .any(|matchers| matchers.iter().all(|m| m.matches(info)))
+    }
+}
+
+#[derive(Debug)]
+struct Swallowed {
+    terminal: WindowHandle,
+    /// where the terminal goes back to once the window that swallowed it is gone.
+    tags: Vec<TagHandle>,
+}
+
+/// hides a terminal while a window started from it is open, giving that window the terminal's tile.
+///
+/// windows are related through their process: a window swallows a terminal when the terminal's process is
+/// one of its ancestors. programs started through `uwsm app` are children of systemd rather than of the
+/// terminal, so they never swallow.
+#[derive(Debug)]
+pub struct Swallowing {
+    config: SwallowConfig,
+    /// window -> the terminal it swallowed.
+    swallowed: Mutex<HashMap<WindowHandle, Swallowed>>,
+}
+
+impl Swallowing {
+    pub fn new(config: SwallowConfig) -> Swallowing {
+        Swallowing {
+            config,
+            swallowed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// whether `window` is a swallowed terminal, which stays hidden instead of being placed by the rules.
+    /// that includes terminals swallowed before the config was reloaded, as long as a window started from
+    /// them is still open; [`Swallowing::window_mapped`] takes them over again when it sees that window.
+    pub fn claim(&self, window: &WindowHandle) -> bool {
+        if self.config.terminals.is_empty() || !scratchpads::is_hidden(window) {
+            return false;
+        }
+        if self
+            .swallowed
+            .lock()
+            .unwrap()
+            .values()
+            .any(|s| s.terminal == *window)
+        {
+            return true;
+        }
+        let Some(pid) = window.pid() else {
+            return false;
+        };
+        if !self.config.is_terminal(&WindowInfo::from_handle(window)) {
+            return false;
+        }
+        let claimed = window::get_all().any(|other| {
+            other != *window
+                && other
+                    .pid()
+                    .is_some_and(|other| process_tree::ancestors(other).contains(&pid))
+        });
+        if claimed {
+            tracing::info!(terminal = %window.app_id(), "keeping swallowed terminal hidden");
+        }
+        claimed
+    }
+
+    /// let `window` swallow the terminal it was started from, if any. this runs after the window rules, so
+    /// the swallowing window takes the terminal's place even if a rule put it elsewhere.
+    pub fn window_mapped(&self, window: &WindowHandle) {
+        if self.config.terminals.is_empty() {
+            return;
+        }
+        let info = WindowInfo::from_handle(window);
+        if self.config.is_terminal(&info) || self.config.exclude.contains(&info.app_id) {
+            return;
+        }
+        let Some(pid) = window.pid() else {
+            return;
+        };
+        let ancestors = process_tree::ancestors(pid);
+        if ancestors.is_empty() {
+            return;
+        }
+
+        let mut swallowed = self.swallowed.lock().unwrap();
+        if swallowed.contains_key(window) {
+            return;
+        }
+        // the terminal closest up the process tree. several windows can share a process, like the frames of
+        // an emacs daemon, in which case the focused one is the likely parent
+        let Some((_, terminal)) = window::get_all()
+            .filter(|terminal| {
+                terminal != window
+                    && terminal.tiled()
+                    && self.config.is_terminal(&WindowInfo::from_handle(terminal))
+                    && !swallowed.values().any(|s| s.terminal == *terminal)
+            })
+            .filter_map(|terminal| {
+                let depth = ancestors.iter().position(|&p| Some(p) == terminal.pid())?;
+                Some(((depth, !terminal.focused()), terminal))
+            })
+            .min_by_key(|(key, _)| *key)
+        else {
+            return;
+        };
+
+        // a terminal that is already hidden was swallowed before the config was reloaded, so it goes back to
+        // where its window is now
+        let tags = if scratchpads::is_hidden(&terminal) {
+            window.tags().collect()
+        } else {
+            let tags = terminal.tags().collect::<Vec<_>>();
+            window.set_tags(tags.clone());
+            window.swap(&terminal);
+            scratchpads::hide(&terminal);
+            tags
+        };
+        tracing::info!(app_id = %info.app_id, terminal = %terminal.app_id(), "swallowing terminal");
+        swallowed.insert(window.clone(), Swallowed { terminal, tags });
+    }
+
+    /// give the terminal swallowed by `window` back its place, or forget the terminal if it's the one that
+    /// went away.
+    pub fn destroyed(&self, window: &WindowHandle) {
+        let mut swallowed = self.swallowed.lock().unwrap();
+        swallowed.retain(|_, s| s.terminal != *window);
+        let Some(Swallowed { terminal, tags }) = swallowed.remove(window) else {
+            return;
+        };
+        drop(swallowed);
+
+        if !window::get_all().any(|w| w == terminal) {
+            return;
+        }
+        tracing::info!(terminal = %terminal.app_id(), "restoring swallowed terminal");
+        terminal.set_tags(tags);
+        if terminal.is_on_active_tag() {
+            terminal.set_focused(true);
+        }
+    }
+}
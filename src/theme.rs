@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// an 8-bit RGBA color.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Rgba {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Rgba {
+        Rgba { r, g, b, a: 0xff }
+    }
+
+    /// the color as `[r, g, b, a]` fractions, the way snowcap wants them.
+    pub fn fractions(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a].map(|c| c as f32 / 0xff as f32)
+    }
+}
+
+impl FromStr for Rgba {
+    type Err = String;
+
+    /// parses `#rrggbb` or `#rrggbbaa`. the `#` is optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("color `{s}` should look like #rrggbb or #rrggbbaa"));
+        }
+        let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap_or(0xff);
+        Ok(Rgba {
+            r: channel(0),
+            g: channel(2),
+            b: channel(4),
+            a: if hex.len() == 8 { channel(6) } else { 0xff },
+        })
+    }
+}
+
+/// what a border looks like. the state colors replace the unfocused color of windows in that state.
+/// fullscreen windows don't get a border at all.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BorderStyle {
+    pub focused: Rgba,
+    pub unfocused: Rgba,
+    pub urgent: Rgba,
+    pub sticky: Option<Rgba>,
+    pub floating: Option<Rgba>,
+    pub maximized: Option<Rgba>,
+    pub thickness: u32,
+}
+
+impl Default for BorderStyle {
+    fn default() -> Self {
+        BorderStyle {
+            focused: Rgba::rgb(0xee, 0xde, 0xce),
+            unfocused: Rgba::rgb(0x3c, 0x2c, 0x1c),
+            urgent: Rgba::rgb(0xcc, 0x24, 0x1d),
+            sticky: None,
+            floating: None,
+            maximized: None,
+            thickness: 2,
+        }
+    }
+}
+
+/// the state of a window that decides its border colors.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct WindowState {
+    pub urgent: bool,
+    pub sticky: bool,
+    pub floating: bool,
+    pub maximized: bool,
+}
+
+impl BorderStyle {
+    /// the focused and unfocused colors of a window in `state`. urgency wins over everything else.
+    pub fn colors(&self, state: WindowState) -> (Rgba, Rgba) {
+        let unfocused = if state.urgent {
+            self.urgent
+        } else if state.sticky
+            && let Some(sticky) = self.sticky
+        {
+            sticky
+        } else if state.maximized
+            && let Some(maximized) = self.maximized
+        {
+            maximized
+        } else if state.floating
+            && let Some(floating) = self.floating
+        {
+            floating
+        } else {
+            self.unfocused
+        };
+        (self.focused, unfocused)
+    }
+
+    /// this style with everything set in `style` replaced.
+    pub fn with(self, style: &StyleOverride) -> BorderStyle {
+        BorderStyle {
+            focused: style.focused.unwrap_or(self.focused),
+            unfocused: style.unfocused.unwrap_or(self.unfocused),
+            urgent: style.urgent.unwrap_or(self.urgent),
+            sticky: style.sticky.or(self.sticky),
+            floating: style.floating.or(self.floating),
+            maximized: style.maximized.or(self.maximized),
+            thickness: style.thickness.unwrap_or(self.thickness),
+        }
+    }
+}
+
+/// a partial [`BorderStyle`] for a single app. anything unset comes from the theme.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct StyleOverride {
+    pub focused: Option<Rgba>,
+    pub unfocused: Option<Rgba>,
+    pub urgent: Option<Rgba>,
+    pub sticky: Option<Rgba>,
+    pub floating: Option<Rgba>,
+    pub maximized: Option<Rgba>,
+    pub thickness: Option<u32>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Theme {
+    pub border: BorderStyle,
+    /// per-app overrides, keyed by app_id.
+    pub apps: HashMap<String, StyleOverride>,
+    /// leave out the border of a window that is alone on its tag.
+    pub smart_borders: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            border: BorderStyle::default(),
+            apps: HashMap::new(),
+            smart_borders: true,
+        }
+    }
+}
+
+impl Theme {
+    /// the border style for windows of `app_id`.
+    pub fn border_for(&self, app_id: &str) -> BorderStyle {
+        match self.apps.get(app_id) {
+            Some(app) => self.border.with(app),
+            None => self.border,
+        }
+    }
+}
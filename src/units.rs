@@ -0,0 +1,142 @@
+use pinnacle_api::process::Command;
+use pinnacle_api::window;
+use pinnacle_api::window::WindowHandle;
+
+use crate::process_tree;
+use crate::uwsm_command::UwsmCommand;
+
+/// the user unit in a cgroup path: the innermost service or scope below the user's service manager.
+/// processes outside of the user manager aren't ours to stop, and neither is the manager's own
+/// `init.scope`.
+fn unit_in(cgroup: &str) -> Option<&str> {
+    let components = cgroup.split('/').collect::<Vec<_>>();
+    let manager = components
+        .iter()
+        .position(|component| component.starts_with("user@"))?;
+    components[manager + 1..]
+        .iter()
+        .rev()
+        .find(|component| component.ends_with(".service") || component.ends_with(".scope"))
+        .copied()
+        .filter(|unit| *unit != "init.scope")
+}
+
+fn unit_of_pid(pid: u32) -> Option<String> {
+    unit_in(&process_tree::cgroup(pid)?).map(str::to_owned)
+}
+
+/// the systemd unit `window` runs in. windows sharing a unit with the config, like those of apps spawned
+/// without `uwsm app`, are left out: acting on that unit would take down the session.
+pub fn unit_of(window: &WindowHandle) -> Option<String> {
+    let unit = unit_of_pid(window.pid()?)?;
+    if unit_of_pid(std::process::id()).is_some_and(|own| own == unit) {
+        tracing::warn!(app_id = %window.app_id(), %unit, "window runs in the compositor's unit");
+        return None;
+    }
+    Some(unit)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum UnitAction {
+    Stop,
+    Restart,
+    /// SIGKILL every process in the unit, for apps that don't react to being stopped.
+    Kill,
+}
+
+/// stop, restart or kill the unit of the focused window, taking the whole app with it.
+pub fn act_on_focused(action: UnitAction) {
+    let Some(window) = window::get_focused() else {
+        return;
+    };
+    let Some(unit) = unit_of(&window) else {
+        return;
+    };
+    let args = match action {
+        UnitAction::Stop => vec!["stop", &unit],
+        // scopes only wrap processes that were started elsewhere, so systemd can't start them again
+        UnitAction::Restart if unit.ends_with(".scope") => {
+            tracing::warn!(%unit, "scopes can't be restarted");
+            return;
+        }
+        UnitAction::Restart => vec!["restart", &unit],
+        UnitAction::Kill => vec!["kill", "--signal=SIGKILL", &unit],
+    };
+    tracing::info!(app_id = %window.app_id(), %unit, ?action, "acting on the window's unit");
+    Command::new("systemctl").arg("--user").args(args).spawn();
+}
+
+/// open the status and the journal of the focused window's unit in `terminal`.
+pub fn show_focused(terminal: &str) {
+    let Some(unit) = window::get_focused().and_then(|window| unit_of(&window)) else {
+        return;
+    };
+    UwsmCommand::new(terminal)
+        .args([
+            "-e",
+            "sh",
+            "-c",
+            r#"systemctl --user status --no-pager -- "$1"; exec journalctl --user --follow --unit "$1""#,
+            "sh",
+            &unit,
+        ])
+        .spawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANAGER: &str = "/user.slice/user-1000.slice/user@1000.service";
+
+    #[test]
+    fn unit_in_finds_app_scopes_and_services() {
+        assert_eq!(
+            unit_in(&format!("{MANAGER}/app.slice/app-pinnacle-foot-1234.scope")),
+            Some("app-pinnacle-foot-1234.scope")
+        );
+        assert_eq!(
+            unit_in(&format!(
+                "{MANAGER}/app.slice/app-pinnacle-emacs@abcd.service"
+            )),
+            Some("app-pinnacle-emacs@abcd.service")
+        );
+    }
+
+    #[test]
+    fn unit_in_looks_through_nested_slices() {
+        assert_eq!(
+            unit_in(&format!(
+                "{MANAGER}/app.slice/app-graphical.slice/app-pinnacle-firefox-42.scope"
+            )),
+            Some("app-pinnacle-firefox-42.scope")
+        );
+        // a service's own sub-cgroups belong to the service
+        assert_eq!(
+            unit_in(&format!(
+                "{MANAGER}/app.slice/app-pinnacle-steam-7.scope/payload"
+            )),
+            Some("app-pinnacle-steam-7.scope")
+        );
+    }
+
+    #[test]
+    fn unit_in_finds_the_compositors_own_unit() {
+        // apps spawned without `uwsm app` share this unit, which `unit_of` refuses to act on
+        assert_eq!(
+            unit_in(&format!(
+                "{MANAGER}/session.slice/wayland-wm@pinnacle.service"
+            )),
+            Some("wayland-wm@pinnacle.service")
+        );
+    }
+
+    #[test]
+    fn unit_in_leaves_out_units_that_arent_ours() {
+        assert_eq!(unit_in(&format!("{MANAGER}/init.scope")), None);
+        assert_eq!(unit_in(MANAGER), None);
+        assert_eq!(unit_in("/user.slice/user-1000.slice/session-2.scope"), None);
+        assert_eq!(unit_in("/system.slice/sshd.service"), None);
+        assert_eq!(unit_in("/"), None);
+    }
+}
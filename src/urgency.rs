@@ -0,0 +1,142 @@
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use pinnacle_api::process::Command;
+use pinnacle_api::window;
+use pinnacle_api::window::WindowHandle;
+
+use crate::focus_history;
+use crate::scratchpads;
+use crate::window_rules::Matcher;
+use crate::window_rules::WindowInfo;
+
+/// the eww variable the urgent windows are published to, as a JSON array of
+/// `{"app_id", "title", "tags", "output"}` objects. the bar declares it with `(defvar urgent_windows "[]")`.
+const EWW_VARIABLE: &str = "urgent_windows";
+
+/// recognizes a window asking for attention from its new title. all matchers have to match.
+///
+/// pinnacle doesn't forward the urgency hints of clients, so a title change into a known "unread" form,
+/// like slack's `* ` prefix, is the best signal there is.
+#[derive(Debug, Clone)]
+pub struct AttentionRule {
+    pub name: String,
+    pub matchers: Vec<Matcher>,
+}
+
+impl AttentionRule {
+    pub fn matches(&self, info: &WindowInfo) -> bool {
+        self.matchers.iter().all(|m| m.matches(info))
+    }
+}
+
+/// keeps track of the windows asking for attention, oldest first.
+#[derive(Debug)]
+pub struct Urgency {
+    rules: Vec<AttentionRule>,
+    urgent: Mutex<Vec<WindowHandle>>,
+}
+
+impl Urgency {
+    pub fn new(rules: Vec<AttentionRule>) -> Urgency {
+        Urgency {
+            rules,
+            urgent: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn is_urgent(&self, window: &WindowHandle) -> bool {
+        self.urgent.lock().unwrap().contains(window)
+    }
+
+    /// mark `window` urgent if its new title asks for attention and the user isn't looking at it. returns
+    /// whether the window became urgent.
+    pub fn title_changed(&self, window: &WindowHandle) -> bool {
+        if window.focused() && window.is_on_active_tag() {
+            return false;
+        }
+        let info = WindowInfo::from_handle(window);
+        let Some(rule) = self.rules.iter().find(|rule| rule.matches(&info)) else {
+            return false;
+        };
+        let mut urgent = self.urgent.lock().unwrap();
+        if urgent.contains(window) {
+            return false;
+        }
+        tracing::info!(rule = %rule.name, app_id = %info.app_id, "window wants attention");
+        urgent.push(window.clone());
+        true
+    }
+
+    /// the window got focus or went away, so it doesn't need attention anymore. returns whether it was
+    /// urgent.
+    pub fn clear(&self, window: &WindowHandle) -> bool {
+        let mut urgent = self.urgent.lock().unwrap();
+        let len = urgent.len();
+        urgent.retain(|w| w != window);
+        urgent.len() != len
+    }
+
+    /// switch to the tag and output of the oldest urgent window and focus it.
+    pub fn jump_to_oldest(&self) {
+        let oldest = {
+            let alive = window::get_all().collect::<Vec<_>>();
+            let mut urgent = self.urgent.lock().unwrap();
+            urgent.retain(|window| alive.contains(window));
+            urgent
+                .iter()
+                .find(|window| !scratchpads::is_hidden(window))
+                .cloned()
+        };
+        // focusing clears the urgency through the focus signal
+        if let Some(window) = oldest {
+            focus_history::jump_to(&window);
+        }
+    }
+
+    /// send the urgent windows to the bar.
+    pub fn publish(&self) {
+        let urgent = self.urgent.lock().unwrap().clone();
+        let mut json = String::from("[");
+        for (idx, window) in urgent.iter().enumerate() {
+            let tags = window
+                .tags()
+                .map(|tag| json_string(&tag.name()))
+                .collect::<Vec<_>>()
+                .join(",");
+            let output = window.output().map(|op| op.name()).unwrap_or_default();
+            let _ = write!(
+                json,
+                r#"{}{{"app_id":{},"title":{},"tags":[{tags}],"output":{}}}"#,
+                if idx == 0 { "" } else { "," },
+                json_string(&window.app_id()),
+                json_string(&window.title()),
+                json_string(&output),
+            );
+        }
+        json.push(']');
+
+        Command::new("eww")
+            .args(["update", &format!("{EWW_VARIABLE}={json}")])
+            .spawn();
+    }
+}
+
+/// quote `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str(r#"\""#),
+            '\\' => quoted.push_str(r"\\"),
+            '\n' => quoted.push_str(r"\n"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -2,6 +2,12 @@ use std::{collections::HashMap, ffi::OsStr, fmt::Display, path::Path};
 
 use pinnacle_api::process::{Child, Command};
 
+use crate::launch_context;
+
+mod resources;
+
+pub use resources::{CpuQuota, MemoryLimit, Nice, OomScoreAdjust, TasksMax, Weight};
+
 /// `Command` wrapper that spawns via `uwsm app`. this ensures processes are started within an
 /// appropriate systemd slice, with a matching unit.
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -83,8 +89,39 @@ impl UwsmCommand {
     }
 
     /// Spawns this command, returning the spawned process's standard io, if any.
+    ///
+    /// the launch is remembered so the app's window can be put where it was launched from.
     pub fn spawn(self) -> Option<Child> {
-        Command::from(self).spawn()
+        let Some(token) = launch_context::begin() else {
+            return Command::from(self).spawn();
+        };
+        let child = Command::from(self.launch_token(&token)).spawn();
+        launch_context::spawned(&token, child.as_ref());
+        child
+    }
+
+    /// pass the launch token to the app. a scope runs the app as a child of uwsm, so it inherits uwsm's
+    /// environment, but systemd rejects `Environment=` on scopes. a service starts the app from the user
+    /// manager, so there the token has to go into the unit's environment, next to what's already there.
+    fn launch_token(self, token: &str) -> Self {
+        let var = format!("{}={token}", launch_context::TOKEN_VAR);
+        if self.unit_type != Some(UnitType::Service) {
+            return self.env(launch_context::TOKEN_VAR, token);
+        }
+        let environment = match self
+            .unit_properties
+            .as_ref()
+            .and_then(|up| up.get("Environment"))
+        {
+            Some(existing) => format!("{existing} {var}"),
+            None => var,
+        };
+        self.unit_property("Environment", environment)
+    }
+
+    /// whether the command is `once` or `unique`, so that spawning it may do nothing.
+    pub fn is_once_or_unique(&self) -> bool {
+        self.once || self.unique
     }
 
     /// Adds an argument to the command.
@@ -158,32 +195,82 @@ impl UwsmCommand {
         self.unit_properties = Some(up);
         self
     }
+
+    /// Hard memory limit -- the unit's processes are OOM-killed beyond it.
+    pub fn memory_max(self, limit: MemoryLimit) -> Self {
+        self.unit_property("MemoryMax", limit)
+    }
+
+    /// Soft memory limit -- the unit is throttled and reclaimed from heavily beyond it.
+    pub fn memory_high(self, limit: MemoryLimit) -> Self {
+        self.unit_property("MemoryHigh", limit)
+    }
+
+    /// Cap the CPU time of the unit.
+    pub fn cpu_quota(self, quota: CpuQuota) -> Self {
+        self.unit_property("CPUQuota", quota)
+    }
+
+    /// Share of CPU time when the CPU is contended.
+    pub fn cpu_weight(self, weight: Weight) -> Self {
+        self.unit_property("CPUWeight", weight)
+    }
+
+    /// Share of IO bandwidth when the disks are contended.
+    pub fn io_weight(self, weight: Weight) -> Self {
+        self.unit_property("IOWeight", weight)
+    }
+
+    /// Cap the number of processes and threads of the unit.
+    pub fn tasks_max(self, tasks: TasksMax) -> Self {
+        self.unit_property("TasksMax", tasks)
+    }
+
+    /// Scheduling priority of the process. this applies when the process is started, which only
+    /// services do, so this makes the unit a service.
+    pub fn nice(self, nice: Nice) -> Self {
+        self.unit_type(UnitType::Service)
+            .unit_property("Nice", nice)
+    }
+
+    /// How eagerly the OOM killer picks the process. like [`UwsmCommand::nice`], this makes the unit a
+    /// service.
+    pub fn oom_score_adjust(self, adjust: OomScoreAdjust) -> Self {
+        self.unit_type(UnitType::Service)
+            .unit_property("OOMScoreAdjust", adjust)
+    }
 }
 
-impl From<UwsmCommand> for Command {
-    fn from(value: UwsmCommand) -> Self {
-        let app_name: &str = Path::new(&value.command)
+impl UwsmCommand {
+    /// the `uwsm app` invocation that starts the app, up to the `--` before the command.
+    fn uwsm_args(&self) -> Vec<String> {
+        let app_name: &str = Path::new(&self.command)
             .file_prefix()
             .and_then(OsStr::to_str)
-            .unwrap_or(&value.command);
+            .unwrap_or(&self.command);
         let mut uwsm_cmd = vec![
             "uwsm".to_owned(),
             "app".to_owned(),
             "-a".to_owned(),
             app_name.to_owned(),
         ];
-        if let Some(ut) = value.unit_type {
+        if let Some(ut) = self.unit_type {
             uwsm_cmd.append(&mut vec!["-t".to_owned(), ut.to_string()]);
         }
-        if let Some(s) = value.slice_selector {
+        if let Some(s) = &self.slice_selector {
             uwsm_cmd.append(&mut vec!["-s".to_owned(), s.to_string()]);
         }
-        for (k, v) in value.unit_properties.into_iter().flatten() {
+        for (k, v) in self.unit_properties.iter().flatten() {
             uwsm_cmd.append(&mut vec!["-p".to_owned(), format!("{k}={v}")]);
         }
         uwsm_cmd.extend(["--".to_owned(), "systemd-cat".to_owned()]);
+        uwsm_cmd
+    }
+}
 
-        let mut cmd = Command::with_shell(uwsm_cmd, value.command);
+impl From<UwsmCommand> for Command {
+    fn from(value: UwsmCommand) -> Self {
+        let mut cmd = Command::with_shell(value.uwsm_args(), value.command);
         cmd.args(value.args);
         cmd.envs(value.envs);
         if value.once {
@@ -195,3 +282,65 @@ impl From<UwsmCommand> for Command {
         cmd
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn launch_token_goes_into_the_environment_of_scopes() {
+        let command = UwsmCommand::new("foot").launch_token("1-0");
+        assert_eq!(
+            command
+                .envs
+                .get(launch_context::TOKEN_VAR)
+                .map(String::as_str),
+            Some("1-0")
+        );
+        assert_eq!(
+            command.uwsm_args(),
+            ["uwsm", "app", "-a", "foot", "--", "systemd-cat"]
+        );
+
+        let command = UwsmCommand::new("foot")
+            .unit_type(UnitType::Scope)
+            .launch_token("1-0");
+        assert!(command.envs.contains_key(launch_context::TOKEN_VAR));
+        assert!(
+            !command
+                .uwsm_args()
+                .iter()
+                .any(|arg| arg.starts_with("Environment="))
+        );
+    }
+
+    #[test]
+    fn launch_token_is_added_to_the_environment_of_services() {
+        let command = UwsmCommand::new("foot")
+            .unit_type(UnitType::Service)
+            .launch_token("1-0");
+        assert!(command.envs.is_empty());
+        assert_eq!(
+            command.uwsm_args(),
+            [
+                "uwsm",
+                "app",
+                "-a",
+                "foot",
+                "-t",
+                "service",
+                "-p",
+                "Environment=PINNACLE_LAUNCH_TOKEN=1-0",
+                "--",
+                "systemd-cat"
+            ]
+        );
+
+        let command = UwsmCommand::new("foot")
+            .unit_type(UnitType::Service)
+            .unit_property("Environment", "A=1")
+            .launch_token("1-0");
+        let args = command.uwsm_args();
+        assert!(args.contains(&"Environment=A=1 PINNACLE_LAUNCH_TOKEN=1-0".to_owned()));
+    }
+}
@@ -0,0 +1,259 @@
+//! typed values for the cgroup and process properties of a unit. the constructors return `None` for
+//! values systemd would reject. they're `const fn`s, so a limit unwrapped in a `const` block is checked at
+//! compile time:
+//!
+//! ```ignore
+//! UwsmCommand::new("firefox").memory_max(const { MemoryLimit::gib(4).unwrap() })
+//! ```
+
+use std::fmt::Display;
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+const GIB: u64 = 1024 * MIB;
+const TIB: u64 = 1024 * GIB;
+
+/// a limit of `n * unit` bytes, or `None` if that overflows.
+const fn scaled(n: u64, unit: u64) -> Option<MemoryLimit> {
+    match n.checked_mul(unit) {
+        Some(bytes) => MemoryLimit::bytes(bytes),
+        None => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Limit {
+    Absolute(u64),
+    Percent(u8),
+    Infinity,
+}
+
+/// a memory limit for `MemoryMax` or `MemoryHigh`: a size, a share of the physical memory, or none.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MemoryLimit(Limit);
+
+impl MemoryLimit {
+    pub const INFINITY: MemoryLimit = MemoryLimit(Limit::Infinity);
+
+    /// `None` for 0 bytes, which leaves no room to run.
+    pub const fn bytes(bytes: u64) -> Option<MemoryLimit> {
+        if bytes > 0 {
+            Some(MemoryLimit(Limit::Absolute(bytes)))
+        } else {
+            None
+        }
+    }
+
+    pub const fn kib(n: u64) -> Option<MemoryLimit> {
+        scaled(n, KIB)
+    }
+
+    pub const fn mib(n: u64) -> Option<MemoryLimit> {
+        scaled(n, MIB)
+    }
+
+    pub const fn gib(n: u64) -> Option<MemoryLimit> {
+        scaled(n, GIB)
+    }
+
+    /// a share of the physical memory, from 1 to 100 percent.
+    pub const fn percent(percent: u8) -> Option<MemoryLimit> {
+        if percent > 0 && percent <= 100 {
+            Some(MemoryLimit(Limit::Percent(percent)))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for MemoryLimit {
+    /// the largest binary suffix that fits exactly, the way systemd reads it back.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Limit::Absolute(bytes) => {
+                match [(TIB, "T"), (GIB, "G"), (MIB, "M"), (KIB, "K")]
+                    .into_iter()
+                    .find(|(unit, _)| bytes % unit == 0)
+                {
+                    Some((unit, suffix)) => write!(f, "{}{suffix}", bytes / unit),
+                    None => write!(f, "{bytes}"),
+                }
+            }
+            Limit::Percent(percent) => write!(f, "{percent}%"),
+            Limit::Infinity => write!(f, "infinity"),
+        }
+    }
+}
+
+/// the CPU time a unit may use for `CPUQuota`, in percent of one CPU. above 100% it may use more than one.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CpuQuota(u32);
+
+impl CpuQuota {
+    /// `None` for 0%, which never lets the unit run.
+    pub const fn percent(percent: u32) -> Option<CpuQuota> {
+        if percent > 0 {
+            Some(CpuQuota(percent))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for CpuQuota {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+/// a relative share for `CPUWeight` or `IOWeight`, from 1 to 10000. units get 100 by default.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Weight(u16);
+
+impl Weight {
+    pub const DEFAULT: Weight = Weight(100);
+
+    pub const fn new(weight: u16) -> Option<Weight> {
+        if weight >= 1 && weight <= 10000 {
+            Some(Weight(weight))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for Weight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// the most tasks, i.e. processes and threads, a unit may have for `TasksMax`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TasksMax(Limit);
+
+impl TasksMax {
+    pub const INFINITY: TasksMax = TasksMax(Limit::Infinity);
+
+    /// `None` for 0, a unit needs at least one task.
+    pub const fn count(count: u64) -> Option<TasksMax> {
+        if count > 0 {
+            Some(TasksMax(Limit::Absolute(count)))
+        } else {
+            None
+        }
+    }
+
+    /// a share of the system's task limit, from 1 to 100 percent.
+    pub const fn percent(percent: u8) -> Option<TasksMax> {
+        if percent > 0 && percent <= 100 {
+            Some(TasksMax(Limit::Percent(percent)))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for TasksMax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Limit::Absolute(count) => write!(f, "{count}"),
+            Limit::Percent(percent) => write!(f, "{percent}%"),
+            Limit::Infinity => write!(f, "infinity"),
+        }
+    }
+}
+
+/// the scheduling priority of the unit's processes for `Nice`, from -20 (first) to 19 (last).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Nice(i8);
+
+impl Nice {
+    pub const fn new(nice: i8) -> Option<Nice> {
+        if nice >= -20 && nice <= 19 {
+            Some(Nice(nice))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for Nice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// how eagerly the OOM killer picks the unit's processes for `OOMScoreAdjust`, from -1000 (never) to
+/// 1000 (first).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct OomScoreAdjust(i16);
+
+impl OomScoreAdjust {
+    pub const fn new(adjust: i16) -> Option<OomScoreAdjust> {
+        if adjust >= -1000 && adjust <= 1000 {
+            Some(OomScoreAdjust(adjust))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for OomScoreAdjust {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_limits_use_the_largest_exact_suffix() {
+        let limit = |limit: Option<MemoryLimit>| limit.unwrap().to_string();
+        assert_eq!(limit(MemoryLimit::gib(4)), "4G");
+        assert_eq!(limit(MemoryLimit::mib(1536)), "1536M");
+        assert_eq!(limit(MemoryLimit::gib(2048)), "2T");
+        assert_eq!(limit(MemoryLimit::bytes(1000)), "1000");
+        assert_eq!(limit(MemoryLimit::percent(50)), "50%");
+        assert_eq!(MemoryLimit::INFINITY.to_string(), "infinity");
+    }
+
+    #[test]
+    fn memory_limits_systemd_would_reject_are_none() {
+        assert_eq!(MemoryLimit::bytes(0), None);
+        assert_eq!(MemoryLimit::gib(0), None);
+        assert_eq!(MemoryLimit::gib(u64::MAX), None);
+        assert_eq!(MemoryLimit::percent(0), None);
+        assert_eq!(MemoryLimit::percent(101), None);
+        assert!(MemoryLimit::percent(100).is_some());
+    }
+
+    #[test]
+    fn out_of_range_values_are_none() {
+        assert_eq!(CpuQuota::percent(0), None);
+        assert_eq!(Weight::new(0), None);
+        assert_eq!(Weight::new(10001), None);
+        assert_eq!(
+            Weight::new(10000).map(|w| w.to_string()).as_deref(),
+            Some("10000")
+        );
+        assert_eq!(TasksMax::count(0), None);
+        assert_eq!(TasksMax::percent(101), None);
+        assert_eq!(Nice::new(-21), None);
+        assert_eq!(Nice::new(20), None);
+        assert_eq!(
+            Nice::new(-20).map(|n| n.to_string()).as_deref(),
+            Some("-20")
+        );
+        assert_eq!(OomScoreAdjust::new(1001), None);
+        assert!(OomScoreAdjust::new(-1000).is_some());
+    }
+
+    #[test]
+    fn constructors_can_be_checked_at_compile_time() {
+        const LIMIT: MemoryLimit = MemoryLimit::gib(4).unwrap();
+        assert_eq!(LIMIT.to_string(), "4G");
+    }
+}
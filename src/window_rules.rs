@@ -0,0 +1,461 @@
+use pinnacle_api::output;
+use pinnacle_api::tag;
+use pinnacle_api::util::Size;
+use pinnacle_api::window;
+use pinnacle_api::window::VrrDemand;
+use pinnacle_api::window::WindowHandle;
+use regex::Regex;
+
+/// a plain description of a window, decoupled from the compositor so rules can be evaluated (and tested)
+/// without a running pinnacle instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowInfo {
+    pub app_id: String,
+    pub title: String,
+    /// the X11 `WM_CLASS` for XWayland windows, native wayland windows don't have one. pinnacle doesn't
+    /// expose the class separately, it reports it as the app_id, so this is the app_id of XWayland windows.
+    pub class: Option<String>,
+    /// the size the window was mapped with, if the compositor knows it yet.
+    pub size: Option<Size>,
+    pub xwayland: bool,
+}
+
+impl WindowInfo {
+    /// snapshot the matchable properties of a live window.
+    pub fn from_handle(window: &WindowHandle) -> WindowInfo {
+        let app_id = window.app_id();
+        let xwayland = window.is_xwayland();
+        WindowInfo {
+            // pinnacle reports the X11 class as the app_id for XWayland windows
+            class: xwayland.then(|| app_id.clone()),
+            app_id,
+            title: window.title(),
+            size: window.size(),
+            xwayland,
+        }
+    }
+}
+
+/// a single predicate on a [`WindowInfo`]. a rule matches when all of its matchers do.
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    AppId(String),
+    AppIdRegex(Regex),
+    TitleRegex(Regex),
+    /// the app_id of an XWayland window, which is its X11 class. never matches native wayland windows.
+    Class(String),
+    /// inclusive bounds on the initial size of the window. windows without a known size never match.
+    InitialSize {
+        min: Option<Size>,
+        max: Option<Size>,
+    },
+    XWayland(bool),
+}
+
+impl Matcher {
+    pub fn matches(&self, info: &WindowInfo) -> bool {
+        match self {
+            Matcher::AppId(app_id) => info.app_id == *app_id,
+            Matcher::AppIdRegex(re) => re.is_match(&info.app_id),
+            Matcher::TitleRegex(re) => re.is_match(&info.title),
+            Matcher::Class(class) => info.class.as_ref() == Some(class),
+            Matcher::InitialSize { min, max } => info.size.is_some_and(|size| {
+                min.is_none_or(|min| size.w >= min.w && size.h >= min.h)
+                    && max.is_none_or(|max| size.w <= max.w && size.h <= max.h)
+            }),
+            Matcher::XWayland(xwayland) => info.xwayland == *xwayland,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum VrrPolicy {
+    Always,
+    WhenFullscreen,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Decoration {
+    ServerSide,
+    ClientSide,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Action {
+    /// tag names, resolved on the window's target output.
+    Tags(Vec<String>),
+    Maximized(bool),
+    Fullscreen(bool),
+    Floating(bool),
+    Geometry(Geometry),
+    /// name of the output to move the window to.
+    Output(String),
+    Vrr(VrrPolicy),
+    Decoration(Decoration),
+}
+
+#[derive(Debug, Clone)]
+pub struct WindowRule {
+    pub name: String,
+    /// rules are evaluated from the highest priority to the lowest. ties keep declaration order.
+    pub priority: i32,
+    pub matchers: Vec<Matcher>,
+    pub actions: Vec<Action>,
+    /// keep evaluating lower priority rules after this one matched.
+    pub fall_through: bool,
+}
+
+impl WindowRule {
+    pub fn new(name: impl ToString) -> WindowRule {
+        WindowRule {
+            name: name.to_string(),
+            priority: 0,
+            matchers: Vec::new(),
+            actions: Vec::new(),
+            fall_through: false,
+        }
+    }
+
+    pub fn priority(self, priority: i32) -> Self {
+        WindowRule { priority, ..self }
+    }
+
+    pub fn matching(mut self, matcher: Matcher) -> Self {
+        self.matchers.push(matcher);
+        self
+    }
+
+    pub fn action(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn fall_through(self) -> Self {
+        WindowRule {
+            fall_through: true,
+            ..self
+        }
+    }
+
+    pub fn matches(&self, info: &WindowInfo) -> bool {
+        self.matchers.iter().all(|m| m.matches(info))
+    }
+}
+
+/// the outcome of evaluating a [`RuleSet`]. each property is set by the highest priority rule that
+/// mentions it; anything left `None` is left alone when applied.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ResolvedActions {
+    pub tags: Option<Vec<String>>,
+    pub maximized: Option<bool>,
+    pub fullscreen: Option<bool>,
+    pub floating: Option<bool>,
+    pub geometry: Option<Geometry>,
+    pub output: Option<String>,
+    pub vrr: Option<VrrPolicy>,
+    pub decoration: Option<Decoration>,
+}
+
+impl ResolvedActions {
+    fn merge(&mut self, action: &Action) {
+        match action {
+            Action::Tags(tags) => {
+                self.tags.get_or_insert_with(|| tags.clone());
+            }
+            Action::Maximized(m) => {
+                self.maximized.get_or_insert(*m);
+            }
+            Action::Fullscreen(f) => {
+                self.fullscreen.get_or_insert(*f);
+            }
+            Action::Floating(f) => {
+                self.floating.get_or_insert(*f);
+            }
+            Action::Geometry(g) => {
+                self.geometry.get_or_insert(*g);
+            }
+            Action::Output(o) => {
+                self.output.get_or_insert_with(|| o.clone());
+            }
+            Action::Vrr(v) => {
+                self.vrr.get_or_insert(*v);
+            }
+            Action::Decoration(d) => {
+                self.decoration.get_or_insert(*d);
+            }
+        }
+    }
+}
+
+/// an ordered collection of [`WindowRule`]s with first-match/fall-through semantics.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<WindowRule>,
+}
+
+impl RuleSet {
+    pub fn new(rules: impl IntoIterator<Item = WindowRule>) -> RuleSet {
+        let mut rules = rules.into_iter().collect::<Vec<_>>();
+        // stable, so equal priorities keep the order they were declared in
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+        RuleSet { rules }
+    }
+
+    pub fn rules(&self) -> &[WindowRule] {
+        &self.rules
+    }
+
+    /// evaluate the rules against `info`, stopping at the first matching rule that doesn't fall through.
+    pub fn evaluate(&self, info: &WindowInfo) -> ResolvedActions {
+        let mut resolved = ResolvedActions::default();
+        for rule in self.rules.iter().filter(|rule| rule.matches(info)) {
+            tracing::debug!(rule = %rule.name, app_id = %info.app_id, "window rule matched");
+            rule.actions
+                .iter()
+                .for_each(|action| resolved.merge(action));
+            if !rule.fall_through {
+                break;
+            }
+        }
+        resolved
+    }
+
+    /// evaluate the rules against a live window and apply the result. returns whether a rule put the
+    /// window on an output or tags.
+    pub fn apply(&self, window: &WindowHandle) -> bool {
+        let actions = self.evaluate(&WindowInfo::from_handle(window));
+        apply_actions(window, &actions);
+        actions.output.is_some() || actions.tags.is_some()
+    }
+}
+
+/// apply resolved actions to a window. decoration and VRR fall back to server side decorations and
+/// VRR-when-fullscreen when no rule sets them.
+pub fn apply_actions(window: &WindowHandle, actions: &ResolvedActions) {
+    let target_output = actions
+        .output
+        .as_deref()
+        .and_then(output::get_by_name)
+        .inspect(|op| window.move_to_output(op));
+
+    if let Some(tags) = &actions.tags {
+        window.set_tags(tags.iter().filter_map(|name| match &target_output {
+            Some(op) => tag::get_on_output(name, op),
+            None => tag::get(name),
+        }));
+    }
+    if let Some(floating) = actions.floating {
+        window.set_floating(floating);
+    }
+    if let Some(Geometry { x, y, w, h }) = actions.geometry {
+        window.set_geometry(x, y, w, h);
+    }
+    if let Some(maximized) = actions.maximized {
+        window.set_maximized(maximized);
+    }
+    if let Some(fullscreen) = actions.fullscreen {
+        window.set_fullscreen(fullscreen);
+    }
+
+    window.set_decoration_mode(match actions.decoration.unwrap_or(Decoration::ServerSide) {
+        Decoration::ServerSide => window::DecorationMode::ServerSide,
+        Decoration::ClientSide => window::DecorationMode::ClientSide,
+    });
+    window.set_vrr_demand(match actions.vrr.unwrap_or(VrrPolicy::WhenFullscreen) {
+        VrrPolicy::Always => VrrDemand::always(),
+        VrrPolicy::WhenFullscreen => VrrDemand::when_fullscreen(),
+        VrrPolicy::Never => VrrDemand::never(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(app_id: &str, title: &str) -> WindowInfo {
+        WindowInfo {
+            app_id: app_id.to_owned(),
+            title: title.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn regex(pattern: &str) -> Regex {
+        Regex::new(pattern).unwrap()
+    }
+
+    fn names(rules: &RuleSet) -> Vec<&str> {
+        rules
+            .rules()
+            .iter()
+            .map(|rule| rule.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn matchers() {
+        let window = info("org.wezfurlong.wezterm", "vim: notes.md");
+        assert!(Matcher::AppId("org.wezfurlong.wezterm".to_owned()).matches(&window));
+        assert!(!Matcher::AppId("wezterm".to_owned()).matches(&window));
+        assert!(Matcher::AppIdRegex(regex("wezterm$")).matches(&window));
+        assert!(!Matcher::AppIdRegex(regex("^wezterm")).matches(&window));
+        assert!(Matcher::TitleRegex(regex("^vim:")).matches(&window));
+        assert!(!Matcher::TitleRegex(regex("emacs")).matches(&window));
+        assert!(Matcher::XWayland(false).matches(&window));
+        assert!(!Matcher::XWayland(true).matches(&window));
+        assert!(!Matcher::Class("Steam".to_owned()).matches(&window));
+
+        let steam = WindowInfo {
+            class: Some("Steam".to_owned()),
+            xwayland: true,
+            ..info("Steam", "Steam")
+        };
+        assert!(Matcher::Class("Steam".to_owned()).matches(&steam));
+        assert!(!Matcher::Class("steam".to_owned()).matches(&steam));
+        assert!(Matcher::XWayland(true).matches(&steam));
+    }
+
+    #[test]
+    fn initial_size_matcher() {
+        let size = |w, h| Some(Size { w, h });
+        let matcher = Matcher::InitialSize {
+            min: size(100, 100),
+            max: size(800, 600),
+        };
+        let sized = |w, h| WindowInfo {
+            size: size(w, h),
+            ..info("app", "")
+        };
+        assert!(matcher.matches(&sized(100, 100)));
+        assert!(matcher.matches(&sized(800, 600)));
+        assert!(!matcher.matches(&sized(99, 300)));
+        assert!(!matcher.matches(&sized(400, 601)));
+        assert!(!matcher.matches(&info("app", "")));
+
+        let unbounded = Matcher::InitialSize {
+            min: None,
+            max: None,
+        };
+        assert!(unbounded.matches(&sized(1, 1)));
+        assert!(!unbounded.matches(&info("app", "")));
+    }
+
+    #[test]
+    fn rules_are_ordered_by_priority_then_declaration() {
+        let rules = RuleSet::new([
+            WindowRule::new("a"),
+            WindowRule::new("b").priority(10),
+            WindowRule::new("c"),
+            WindowRule::new("d").priority(-1),
+            WindowRule::new("e").priority(10),
+        ]);
+        assert_eq!(names(&rules), ["b", "e", "a", "c", "d"]);
+    }
+
+    #[test]
+    fn evaluation_stops_at_the_first_match() {
+        let rules = RuleSet::new([
+            WindowRule::new("emacs")
+                .matching(Matcher::AppId("emacs".to_owned()))
+                .action(Action::Tags(vec!["I".to_owned()]))
+                .action(Action::Maximized(true)),
+            WindowRule::new("mu4e")
+                .priority(10)
+                .matching(Matcher::AppId("emacs".to_owned()))
+                .matching(Matcher::TitleRegex(regex("mu4e")))
+                .action(Action::Tags(vec!["V".to_owned()])),
+        ]);
+        assert_eq!(
+            rules.evaluate(&info("emacs", "mu4e main")),
+            ResolvedActions {
+                tags: Some(vec!["V".to_owned()]),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            rules.evaluate(&info("emacs", "notes.org")),
+            ResolvedActions {
+                tags: Some(vec!["I".to_owned()]),
+                maximized: Some(true),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            rules.evaluate(&info("firefox", "")),
+            ResolvedActions::default()
+        );
+    }
+
+    #[test]
+    fn fall_through_merges_with_the_highest_priority_winning() {
+        let rules = RuleSet::new([
+            WindowRule::new("all floating")
+                .priority(-10)
+                .action(Action::Floating(true))
+                .action(Action::Maximized(true))
+                .action(Action::Tags(vec!["IX".to_owned()])),
+            WindowRule::new("terminal")
+                .priority(5)
+                .fall_through()
+                .matching(Matcher::AppId("foot".to_owned()))
+                .action(Action::Tags(vec!["VI".to_owned()]))
+                .action(Action::Output("DP-1".to_owned())),
+            WindowRule::new("not maximized")
+                .fall_through()
+                .action(Action::Maximized(false))
+                .action(Action::Output("HDMI-A-1".to_owned())),
+        ]);
+        assert_eq!(
+            rules.evaluate(&info("foot", "")),
+            ResolvedActions {
+                tags: Some(vec!["VI".to_owned()]),
+                maximized: Some(false),
+                floating: Some(true),
+                output: Some("DP-1".to_owned()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn merge_keeps_the_first_value_of_each_property() {
+        let mut resolved = ResolvedActions::default();
+        let geometry = |x| Geometry {
+            x,
+            y: 0,
+            w: 100,
+            h: 100,
+        };
+        for action in [
+            Action::Fullscreen(true),
+            Action::Geometry(geometry(1)),
+            Action::Vrr(VrrPolicy::Never),
+            Action::Decoration(Decoration::ClientSide),
+            Action::Fullscreen(false),
+            Action::Geometry(geometry(2)),
+            Action::Vrr(VrrPolicy::Always),
+            Action::Decoration(Decoration::ServerSide),
+        ] {
+            resolved.merge(&action);
+        }
+        assert_eq!(
+            resolved,
+            ResolvedActions {
+                fullscreen: Some(true),
+                geometry: Some(geometry(1)),
+                vrr: Some(VrrPolicy::Never),
+                decoration: Some(Decoration::ClientSide),
+                ..Default::default()
+            }
+        );
+    }
+}